@@ -3,18 +3,19 @@ use crate::{
     get_with_deviation,
     smart_drawing::{DrawingStrategy, RectangleCorner},
     user_constants::*,
-    Cell, Cross, CrossId, Plant, PlantId, PlantKind, Zoom, AREA_SIZE,
-    CELLS,
+    Batch, Condition, ConditionRegion, Cross, CrossId, IdCounter,
+    ObjectId, Plant, PlantId, PlantKind, Positioned, SpatialGrid,
+    Zoom, AREA_SIZE, AREA_TOPOLOGY,
 };
 use macroquad::prelude::{
-    draw_circle, draw_rectangle, draw_rectangle_ex, draw_text,
-    measure_text, rand::gen_range, vec2, vec3, Circle, Color,
-    DrawRectangleParams, Vec2, GREEN, RED, WHITE,
+    draw_text, measure_text, vec2, Circle, Color, Vec2, ORANGE, RED,
+    SKYBLUE, WHITE,
 };
-use rand::{random, rngs::StdRng, seq::IteratorRandom, Rng};
-use std::collections::{HashMap, HashSet};
-use std::sync::LazyLock;
-use std::{f32::consts::PI, f32::consts::SQRT_2, time::Instant};
+use rand::{rngs::StdRng, Rng};
+use rustc_hash::FxHashMap;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::{f32::consts::PI, f32::consts::SQRT_2};
 
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum ObjectType {
@@ -23,18 +24,36 @@ pub enum ObjectType {
     Cross,
 }
 
-#[derive(Copy, Clone)]
-pub struct FoodInfo<'a> {
-    pub id:        Instant,
+#[derive(Clone)]
+pub struct FoodInfo {
+    pub id:        ObjectId,
     pub food_type: ObjectType,
     pub pos:       Vec2,
     pub energy:    f32,
-    pub viruses:   Option<&'a HashMap<Virus, f32>>,
+    pub viruses:   Option<Viruses>,
+}
+
+/// What a body has decided to do during the tick. Every body decides
+/// from the state the evolution had at the start of the tick, so what
+/// affects other bodies is only resolved once all of them have decided.
+// The actions are kept in a buffer reused across the ticks, so boxing
+// the children would only add an allocation
+#[allow(clippy::large_enum_variant)]
+pub enum Action {
+    /// Nothing but the body itself has changed.
+    None,
+    /// The body has died.
+    Die,
+    /// The body has reached the food, which may have been eaten by an
+    /// older body by then.
+    Eat(FoodInfo),
+    /// The body has divided into the new bodies.
+    Procreate([Body; 2]),
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Status {
-    FollowingTarget(Instant, Vec2, ObjectType),
+    FollowingTarget(ObjectId, Vec2, ObjectType),
     EscapingBody(BodyId, u32),
     Walking(Vec2),
     Idle,
@@ -42,16 +61,35 @@ pub enum Status {
     Undefined,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(
+    Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize,
+)]
 pub enum EatingStrategy {
     Omnivorous,
     Herbivorous,
     Carnivorous,
 }
 
+impl EatingStrategy {
+    pub const ALL: [Self; 3] =
+        [Self::Omnivorous, Self::Herbivorous, Self::Carnivorous];
+
+    #[inline(always)]
+    /// The color the strategy is told apart by on the maps.
+    pub fn get_color(&self) -> Color {
+        match self {
+            EatingStrategy::Omnivorous => ORANGE,
+            EatingStrategy::Herbivorous => SKYBLUE,
+            EatingStrategy::Carnivorous => RED,
+        }
+    }
+}
+
 #[allow(dead_code)]
 #[repr(usize)]
-#[derive(Eq, PartialEq, Hash, Copy, Clone)]
+#[derive(
+    Eq, PartialEq, Hash, Copy, Clone, Debug, Serialize, Deserialize,
+)]
 /// https://github.com/kul-sudo/eportal/blob/main/README.md#viruses
 pub enum Virus {
     SpeedVirus,
@@ -60,9 +98,27 @@ pub enum Virus {
 
 impl Virus {
     pub const ALL: [Self; 2] = [Self::SpeedVirus, Self::VisionVirus];
+
+    #[inline(always)]
+    /// How much energy needs to be spent to get rid of the virus.
+    pub fn get_heal_energy(
+        &self,
+        user_constants: &UserConstants,
+    ) -> f32 {
+        match self {
+            Virus::SpeedVirus => {
+                user_constants.speedvirus_heal_energy
+            }
+            Virus::VisionVirus => {
+                user_constants.visionvirus_heal_energy
+            }
+        }
+    }
 }
 
-#[derive(Eq, Hash, PartialEq, Copy, Clone)]
+#[derive(
+    Eq, Hash, PartialEq, Copy, Clone, Debug, Serialize, Deserialize,
+)]
 /// https://github.com/kul-sudo/eportal/blob/main/README.md#skills
 pub enum Skill {
     DoNotCompeteWithRelatives,
@@ -76,16 +132,6 @@ pub enum Skill {
     AvoidInfectedCrosses,
 }
 
-static SKILLS_HASHMAP: LazyLock<HashSet<Skill>> =
-    LazyLock::new(|| {
-        let mut all_skills = HashSet::new();
-        for skill in Skill::ALL {
-            all_skills.insert(skill);
-        }
-
-        all_skills
-    });
-
 impl Skill {
     pub const ALL: [Self; 9] = [
         Skill::DoNotCompeteWithRelatives,
@@ -100,113 +146,207 @@ impl Skill {
     ];
 }
 
-pub type BodyId = Instant;
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+/// A set of skills, where the bit of a skill is set if the skill is
+/// within the set.
+pub struct SkillSet(u16);
 
-#[derive(Clone, PartialEq)]
-/// https://github.com/kul-sudo/eportal/blob/main/README.md#properties
-pub struct Body {
-    pub pos:                    Vec2,
-    /// Needed for cells: body.pos = body.last_pos after the loop.
-    pub last_pos:               Vec2,
-    pub energy:                 f32,
-    pub speed:                  f32,
-    pub vision_distance:        f32,
-    pub eating_strategy:        EatingStrategy,
-    pub division_threshold:     f32,
-    pub skills:                 HashSet<Skill>,
-    pub viruses:                HashMap<Virus, f32>,
-    pub color:                  Color,
-    pub status:                 Status,
-    pub body_type:              u32,
-    pub lifespan:               f32,
-    initial_speed:              f32,
-    initial_vision_distance:    f32,
-    pub spend_energy_on_vision: bool,
+impl SkillSet {
+    pub const ALL: Self = Self((1 << Skill::ALL.len()) - 1);
+
+    #[inline(always)]
+    pub fn contains(&self, skill: Skill) -> bool {
+        self.0 & (1 << skill as u16) != 0
+    }
+
+    #[inline(always)]
+    pub fn insert(&mut self, skill: Skill) {
+        self.0 |= 1 << skill as u16
+    }
+
+    #[inline(always)]
+    pub fn remove(&mut self, skill: Skill) {
+        self.0 &= !(1 << skill as u16)
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    #[inline(always)]
+    /// The skills within `self` that aren't within `other`.
+    pub fn difference(&self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> impl Iterator<Item = Skill> + '_ {
+        Skill::ALL.into_iter().filter(|skill| self.contains(*skill))
+    }
+
+    #[inline(always)]
+    /// Pick a random skill within the set.
+    pub fn choose(&self, rng: &mut StdRng) -> Option<Skill> {
+        if self.is_empty() {
+            None
+        } else {
+            self.iter().nth(rng.gen_range(0..self.len()))
+        }
+    }
 }
 
-#[macro_export]
-macro_rules! get_visible {
-    ($body:expr, $x:expr, $visible_x:expr) => {
-        // Using these for ease of development
-        let (a, b) = ($body.pos.x, $body.pos.y);
-        let r = $body.vision_distance;
-        let (w, h) = (CELLS.cell_width, CELLS.cell_height);
-        let (m, n) = (CELLS.columns, CELLS.rows);
-
-        // Get the bottommost, topmost, leftmost, and rightmost rows/columns.
-        // If the cell is within the circle or the circle touches the cell, it is
-        // within the rectangle around the circle. Some of those cells are unneeded.
-        let i_min = ((b - r) / h).floor().max(0.0) as usize;
-        let i_max =
-        ((b + r) / h).floor().min((n - 1) as f32) as usize;
-        let j_min = ((a - r) / w).floor().max(0.0) as usize;
-        let j_max =
-        ((a + r) / w).floor().min((m - 1) as f32) as usize;
-
-        // Ditch the unneeded cells
-        let Cell {
-        i: circle_center_i, ..
-        } = CELLS.get_cell_by_pos($body.pos);
-
-        for i in i_min..=i_max {
-        let (
-        // Get the min/max j we have to care about for i
-        j_min_for_i,
-        j_max_for_i,
-        );
+impl FromIterator<Skill> for SkillSet {
+    fn from_iter<I: IntoIterator<Item = Skill>>(iter: I) -> Self {
+        let mut skills = Self::default();
+
+        for skill in iter {
+            skills.insert(skill);
+        }
+
+        skills
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+/// The viruses a body or a cross has been infected with, along with
+/// the energy spent on healing from each of them.
+pub struct Viruses([Option<f32>; Virus::ALL.len()]);
 
-        if i == circle_center_i {
-        (j_min_for_i, j_max_for_i) = (j_min, j_max);
+impl Viruses {
+    #[inline(always)]
+    pub fn contains(&self, virus: Virus) -> bool {
+        self.0[virus as usize].is_some()
+    }
+
+    #[inline(always)]
+    /// Add the virus if it isn't there yet and return whether it's been
+    /// added.
+    pub fn insert(
+        &mut self,
+        virus: Virus,
+        energy_spent_for_healing: f32,
+    ) -> bool {
+        let entry = &mut self.0[virus as usize];
+
+        if entry.is_some() {
+            false
         } else {
-        let i_for_line =
-        if i < circle_center_i { i + 1 } else { i };
-
-        let delta = r
-        * (1.0
-        - ((i_for_line as f32 * h - b) / r)
-        .powi(2))
-        .sqrt();
-        (j_min_for_i, j_max_for_i) = (
-        ((a - delta) / w).floor().max(0.0) as usize,
-        ((a + delta) / w).floor().min((m - 1) as f32)
-        as usize,
-        )
+            *entry = Some(energy_spent_for_healing);
+            true
         }
+    }
 
-        for j in j_min_for_i..=j_max_for_i {
-        // Center of the cell
-        let (center_x, center_y) = (
-        j as f32 * w + w / 2.0,
-        i as f32 * h + h / 2.0,
-        );
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(Option::is_none)
+    }
 
-        // true as usize = 1
-        // false as usize = 0
-        let (i_delta, j_delta) = (
-        (center_y > b) as usize, // If the cell is in the 1st or 2nd quadrant
-        (center_x > a) as usize, // If the cell is in the 1st or 4th quadrant
-        );
+    #[inline(always)]
+    pub fn keys(&self) -> impl Iterator<Item = Virus> + '_ {
+        Virus::ALL.into_iter().filter(|virus| self.contains(*virus))
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> impl Iterator<Item = (Virus, f32)> + '_ {
+        Virus::ALL.into_iter().zip(self.0).filter_map(
+            |(virus, energy_spent_for_healing)| {
+                energy_spent_for_healing.map(
+                    |energy_spent_for_healing| {
+                        (virus, energy_spent_for_healing)
+                    },
+                )
+            },
+        )
+    }
 
-        let fully_covered = (((j + j_delta) as f32) * w
-        - a)
-        .powi(2)
-        + (((i + i_delta) as f32) * h - b).powi(2)
-        < r.powi(2);
+    #[inline(always)]
+    pub fn iter_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (Virus, &mut f32)> {
+        Virus::ALL.into_iter().zip(&mut self.0).filter_map(
+            |(virus, energy_spent_for_healing)| {
+                energy_spent_for_healing.as_mut().map(
+                    |energy_spent_for_healing| {
+                        (virus, energy_spent_for_healing)
+                    },
+                )
+            },
+        )
+    }
 
-        for (x_id, x) in
-        &$x[i][j]
-        {
-        if fully_covered
-        || $body.pos.distance(x.pos)
-        <= $body.vision_distance
+    #[inline(always)]
+    /// Keep only the viruses `f` returns `true` for.
+    pub fn retain(&mut self, mut f: impl FnMut(Virus, f32) -> bool) {
+        for (virus, energy_spent_for_healing) in
+            Virus::ALL.into_iter().zip(&mut self.0)
         {
-        $visible_x.insert(x_id, x);
-        }
-        }
+            if energy_spent_for_healing
+                .is_some_and(|energy| !f(virus, energy))
+            {
+                *energy_spent_for_healing = None;
+            }
         }
+    }
+}
+
+impl FromIterator<(Virus, f32)> for Viruses {
+    fn from_iter<I: IntoIterator<Item = (Virus, f32)>>(
+        iter: I,
+    ) -> Self {
+        let mut viruses = Self::default();
+
+        for (virus, energy_spent_for_healing) in iter {
+            viruses.insert(virus, energy_spent_for_healing);
         }
+
+        viruses
     }
+}
+
+pub type BodyId = ObjectId;
+
+#[derive(Clone, Copy, PartialEq)]
+/// https://github.com/kul-sudo/eportal/blob/main/README.md#properties
+pub struct Body {
+    pub pos:                     Vec2,
+    /// Needed for cells: body.pos = body.last_pos after the loop.
+    pub last_pos:                Vec2,
+    /// The position before the last tick.
+    pub prev_pos:                Vec2,
+    pub energy:                  f32,
+    pub speed:                   f32,
+    pub vision_distance:         f32,
+    pub eating_strategy:         EatingStrategy,
+    pub division_threshold:      f32,
+    pub skills:                  SkillSet,
+    pub viruses:                 Viruses,
+    pub color:                   Color,
+    pub status:                  Status,
+    pub body_type:               u32,
+    pub lifespan:                f32,
+    /// How many ticks the body has lived for.
+    pub age:                     u64,
+    /// The 1st generation is the one the evolution starts with.
+    pub generation:              u32,
+    /// The speed the viruses haven't affected.
+    pub initial_speed:           f32,
+    /// The vision distance the viruses haven't affected.
+    pub initial_vision_distance: f32,
+    pub spend_energy_on_vision:  bool,
+}
+
+impl Positioned for Body {
+    #[inline(always)]
+    fn pos(&self) -> Vec2 {
+        self.pos
     }
+}
 
 #[allow(clippy::too_many_arguments)]
 impl Body {
@@ -217,21 +357,21 @@ impl Body {
         energy: Option<f32>,
         eating_strategy: EatingStrategy,
         division_threshold: Option<f32>,
-        skills: Option<HashSet<Skill>>,
+        skills: Option<SkillSet>,
         color: Color,
         body_type: u32,
-        viruses: Option<HashMap<Virus, f32>>,
+        viruses: Option<Viruses>,
         initial_speed: Option<f32>,
         initial_vision_distance: Option<f32>,
+        user_constants: &UserConstants,
         rng: &mut StdRng,
     ) -> Self {
-        let user_constants = USER_CONSTANTS.read().unwrap();
-
         let speed = get_with_deviation(
             match initial_speed {
                 Some(initial_speed) => initial_speed,
                 None => user_constants.average_speed,
             },
+            user_constants,
             rng,
         );
 
@@ -242,12 +382,14 @@ impl Body {
                 }
                 None => user_constants.average_vision_distance,
             },
+            user_constants,
             rng,
         );
 
         let mut body = Self {
             pos,
             last_pos: pos,
+            prev_pos: pos,
             energy: match energy {
                 Some(energy) => energy / 2.0,
                 None => get_with_deviation(
@@ -261,6 +403,7 @@ impl Body {
                                 .average_energy_omnivorous_herbivorous
                         }
                     },
+                    user_constants,
                     rng,
                 ),
             },
@@ -282,6 +425,7 @@ impl Body {
                             }
                     },
                 },
+                user_constants,
                 rng,
             ),
             skills: match skills {
@@ -289,17 +433,15 @@ impl Body {
                     if rng.gen_range(0.0..1.0)
                         <= user_constants.skills_change_chance
                     {
-                        if random::<bool>() {
-                            if let Some(random_skill) = SKILLS_HASHMAP
-                                .difference(&skills)
-                                .collect::<HashSet<_>>()
-                                .iter()
+                        if rng.gen_bool(0.5) {
+                            if let Some(random_skill) = SkillSet::ALL
+                                .difference(skills)
                                 .choose(rng)
                             {
-                                skills.insert(**random_skill);
+                                skills.insert(random_skill);
                             }
                         } else if let Some(random_skill) =
-                            skills.clone().iter().choose(rng)
+                            skills.choose(rng)
                         {
                             skills.remove(random_skill);
                         }
@@ -307,17 +449,18 @@ impl Body {
 
                     skills
                 }
-                None => HashSet::with_capacity(Skill::ALL.len()),
+                None => SkillSet::default(),
             },
             color,
             status: Status::Undefined,
             body_type,
             lifespan: user_constants.lifespan,
+            age: 0,
+            generation: 1,
             viruses: match viruses {
                 Some(viruses) => viruses,
                 None => {
-                    let mut viruses =
-                        HashMap::with_capacity(Virus::ALL.len());
+                    let mut viruses = Viruses::default();
 
                     if eating_strategy != EatingStrategy::Herbivorous
                     {
@@ -334,9 +477,9 @@ impl Body {
                                     || rng.gen_range(0.0..1.0)
                                         <= virus_chance)
                             {
-                                viruses
-                                    .entry(virus)
-                                    .or_insert(rng.gen_range(
+                                viruses.insert(
+                                    virus,
+                                    rng.gen_range(
                                     0.0..match virus {
                                         Virus::SpeedVirus =>    user_constants.speedvirus_heal_energy
                                         ,
@@ -359,13 +502,48 @@ impl Body {
         };
 
         // Applying the effect of the viruses
-        for virus in body.viruses.clone().keys() {
-            body.apply_virus(*virus);
+        for virus in Virus::ALL {
+            if body.viruses.contains(virus) {
+                body.apply_virus(virus, user_constants);
+            }
         }
 
         body
     }
 
+    /// Replace the speed and the vision distance the body has been
+    /// spawned with, keeping the effect of the viruses.
+    pub fn set_initial_properties(
+        &mut self,
+        speed: Option<f32>,
+        vision_distance: Option<f32>,
+        user_constants: &UserConstants,
+    ) {
+        if let Some(speed) = speed {
+            self.initial_speed = speed;
+            self.speed = speed;
+
+            if self.viruses.contains(Virus::SpeedVirus) {
+                self.apply_virus(Virus::SpeedVirus, user_constants);
+            }
+        }
+
+        if let Some(vision_distance) = vision_distance {
+            self.initial_vision_distance = vision_distance;
+            self.vision_distance = vision_distance;
+
+            if self.viruses.contains(Virus::VisionVirus) {
+                self.apply_virus(Virus::VisionVirus, user_constants);
+            }
+        }
+    }
+
+    #[inline(always)]
+    /// The circle the body sees everything within.
+    pub fn get_vision(&self) -> Circle {
+        Circle::new(self.pos.x, self.pos.y, self.vision_distance)
+    }
+
     #[inline(always)]
     pub fn wrap(&mut self) {
         if self.last_pos.x >= AREA_SIZE.x {
@@ -382,51 +560,71 @@ impl Body {
     }
 
     #[inline(always)]
-    pub fn draw(&self) {
-        let side_length_half = OBJECT_RADIUS / SQRT_2;
+    /// Where the body is drawn when the evolution is `alpha` of the way
+    /// between the last tick and the next one.
+    pub fn get_drawn_pos(&self, alpha: f32) -> Vec2 {
+        let shift = self.pos - self.prev_pos;
+
+        // The body has crossed the border and come out of the opposite
+        // one
+        if shift.x.abs() > AREA_SIZE.x / 2.0
+            || shift.y.abs() > AREA_SIZE.y / 2.0
+        {
+            return self.pos;
+        }
+
+        self.prev_pos + shift * alpha
+    }
+
+    #[inline(always)]
+    pub fn draw(&self, color: Color, alpha: f32, batch: &mut Batch) {
+        let pos = self.get_drawn_pos(alpha);
 
         match self.eating_strategy {
             EatingStrategy::Omnivorous => {
-                let side_length = side_length_half * 2.0;
-                draw_rectangle(
-                    self.pos.x - side_length_half,
-                    self.pos.y - side_length_half,
-                    side_length,
-                    side_length,
-                    self.color,
+                let side_length_half = OBJECT_RADIUS / SQRT_2;
+
+                batch.quad(
+                    [
+                        vec2(-1.0, -1.0),
+                        vec2(1.0, -1.0),
+                        vec2(1.0, 1.0),
+                        vec2(-1.0, 1.0),
+                    ]
+                    .map(|corner| pos + corner * side_length_half),
+                    color,
                 );
             }
-            EatingStrategy::Herbivorous => draw_circle(
-                self.pos.x,
-                self.pos.y,
-                OBJECT_RADIUS,
-                self.color,
-            ),
+            EatingStrategy::Herbivorous => {
+                batch.circle(pos, OBJECT_RADIUS, color)
+            }
             EatingStrategy::Carnivorous => {
-                let side_length = side_length_half * 2.0;
-                draw_rectangle_ex(
-                    self.pos.x,
-                    self.pos.y,
-                    side_length,
-                    side_length,
-                    DrawRectangleParams {
-                        offset:   vec2(0.5, 0.5),
-                        rotation: PI / 4.0,
-                        color:    self.color,
-                    },
+                // A square rotated by 45 degrees
+                batch.quad(
+                    [
+                        vec2(0.0, -1.0),
+                        vec2(1.0, 0.0),
+                        vec2(0.0, 1.0),
+                        vec2(-1.0, 0.0),
+                    ]
+                    .map(|corner| pos + corner * OBJECT_RADIUS),
+                    color,
                 );
             }
         }
 
         if !self.viruses.is_empty() {
-            draw_circle(self.pos.x, self.pos.y, 5.0, RED)
+            batch.circle(pos, 5.0, RED)
         }
     }
 
     #[inline(always)]
-    pub fn draw_info(&self) {
-        let user_constants = USER_CONSTANTS.read().unwrap();
-
+    pub fn draw_info(
+        &self,
+        alpha: f32,
+        user_constants: &UserConstants,
+    ) {
+        let pos = self.get_drawn_pos(alpha);
         let mut to_display_components = Vec::new();
 
         if user_constants.show_energy {
@@ -442,8 +640,19 @@ impl Body {
         }
 
         if user_constants.show_body_type {
-            to_display_components
-                .push(format!("body type = {}", self.body_type));
+            to_display_components.push(
+                // Species are numbered from 1
+                match (self.body_type as usize)
+                    .checked_sub(1)
+                    .and_then(|i| user_constants.species.get(i))
+                {
+                    Some(species) => format!(
+                        "body type = {} ({})",
+                        self.body_type, species.name
+                    ),
+                    None => format!("body type = {}", self.body_type),
+                },
+            );
         }
 
         if user_constants.show_lifespan {
@@ -458,7 +667,7 @@ impl Body {
                 "skills = {:?}",
                 self.skills
                     .iter()
-                    .map(|skill| *skill as u8)
+                    .map(|skill| skill as u8)
                     .collect::<Vec<_>>()
             ));
         }
@@ -468,7 +677,7 @@ impl Body {
                 "viruses = {:?}",
                 self.viruses
                     .keys()
-                    .map(|virus| *virus as u8)
+                    .map(|virus| virus as u8)
                     .collect::<Vec<_>>()
             ));
         }
@@ -477,7 +686,7 @@ impl Body {
             let to_display = to_display_components.join(" | ");
             draw_text(
                 &to_display,
-                self.pos.x
+                pos.x
                     - measure_text(
                         &to_display,
                         None,
@@ -486,7 +695,7 @@ impl Body {
                     )
                     .width
                         / 2.0,
-                self.pos.y - OBJECT_RADIUS - MIN_GAP,
+                pos.y - OBJECT_RADIUS - MIN_GAP,
                 user_constants.body_info_font_size as f32,
                 WHITE,
             );
@@ -494,21 +703,36 @@ impl Body {
     }
 
     #[inline(always)]
-    /// Get the body infected with every virus it doesnn't have yet.
-    pub fn get_viruses(&mut self, viruses: &HashMap<Virus, f32>) {
+    /// Get the body infected with every virus it doesn't have yet.
+    pub fn get_viruses(
+        &mut self,
+        viruses: &Viruses,
+        user_constants: &UserConstants,
+    ) {
         for virus in viruses.keys() {
-            if !self.viruses.contains_key(virus) {
-                self.viruses.insert(*virus, 0.0);
-                self.apply_virus(*virus);
-            }
+            self.get_virus(virus, user_constants);
         }
     }
 
     #[inline(always)]
-    /// Make a virus do its job.
-    pub fn apply_virus(&mut self, virus: Virus) {
-        let user_constants = USER_CONSTANTS.read().unwrap();
+    /// Get the body infected with the virus if it doesn't have it yet.
+    pub fn get_virus(
+        &mut self,
+        virus: Virus,
+        user_constants: &UserConstants,
+    ) {
+        if self.viruses.insert(virus, 0.0) {
+            self.apply_virus(virus, user_constants);
+        }
+    }
 
+    #[inline(always)]
+    /// Make a virus do its job.
+    pub fn apply_virus(
+        &mut self,
+        virus: Virus,
+        user_constants: &UserConstants,
+    ) {
         match virus {
             Virus::SpeedVirus => {
                 self.speed -= self.speed
@@ -626,10 +850,10 @@ impl Body {
 
     #[inline(always)]
     /// Heal from the viruses the body has and spend energy on it.
-    pub fn handle_viruses(&mut self) {
-        let user_constants = USER_CONSTANTS.read().unwrap();
-
-        for (virus, energy_spent_for_healing) in &mut self.viruses {
+    pub fn handle_viruses(&mut self, user_constants: &UserConstants) {
+        for (virus, energy_spent_for_healing) in
+            self.viruses.iter_mut()
+        {
             match virus {
                 Virus::SpeedVirus => {
                     self.energy = (self.energy
@@ -651,15 +875,8 @@ impl Body {
         }
 
         self.viruses.retain(|virus, energy_spent_for_healing| {
-            *energy_spent_for_healing
-                <= match virus {
-                    Virus::SpeedVirus => {
-                        user_constants.speedvirus_heal_energy
-                    }
-                    Virus::VisionVirus => {
-                        user_constants.visionvirus_heal_energy
-                    }
-                }
+            energy_spent_for_healing
+                <= virus.get_heal_energy(user_constants)
         });
     }
 
@@ -694,34 +911,40 @@ impl Body {
     /// Handle the energy. The function returns if the body has run out of energy.
     pub fn handle_energy(
         &mut self,
-        body_id: &BodyId,
-        removed_bodies: &mut HashMap<BodyId, Vec2>,
+        conditions: &[ConditionRegion],
+        user_constants: &UserConstants,
     ) -> bool {
-        let user_constants = USER_CONSTANTS.read().unwrap();
-
-        // The mass is proportional to the energy; to keep the mass up, energy is spent
-        self.energy -= user_constants.energy_spent_const_for_mass
-            * self.energy
-            + user_constants.energy_spent_const_for_skills
-                * self.skills.len() as f32
-            + if self.spend_energy_on_vision {
-                (user_constants
-                    .energy_spent_const_for_vision_distance)
-                    * self.vision_distance.powi(2)
+        // The body spends more energy within a drought
+        let multiplier =
+            if Condition::Drought.is_at(conditions, self.pos) {
+                DROUGHT_ENERGY_SPENT_MULTIPLIER
             } else {
-                0.0
-            }
-            + if let Status::Walking(_) = self.status {
-                (user_constants.energy_spent_const_for_movement)
-                    * self.speed.powi(2)
-                    * self.energy
-            } else {
-                0.0
+                1.0
             };
 
+        // The mass is proportional to the energy; to keep the mass up, energy is spent
+        self.energy -= multiplier
+            * (user_constants.energy_spent_const_for_mass
+                * self.energy
+                + user_constants.energy_spent_const_for_skills
+                    * self.skills.len() as f32
+                + if self.spend_energy_on_vision {
+                    (user_constants
+                        .energy_spent_const_for_vision_distance)
+                        * self.vision_distance.powi(2)
+                } else {
+                    0.0
+                }
+                + if let Status::Walking(_) = self.status {
+                    (user_constants.energy_spent_const_for_movement)
+                        * self.speed.powi(2)
+                        * self.energy
+                } else {
+                    0.0
+                });
+
         if self.energy <= 0.0 {
             self.status = Status::Cross;
-            removed_bodies.insert(*body_id, self.pos);
             true
         } else {
             false
@@ -729,9 +952,10 @@ impl Body {
     }
 
     #[inline(always)]
-    pub fn handle_lifespan(&mut self) {
-        let user_constants = USER_CONSTANTS.read().unwrap();
-
+    pub fn handle_lifespan(
+        &mut self,
+        user_constants: &UserConstants,
+    ) {
         self.lifespan = (self.lifespan
             - user_constants.const_for_lifespan
                 * self.speed.powi(2)
@@ -740,45 +964,170 @@ impl Body {
     }
 
     #[inline(always)]
-    /// Handle procreation and return if one has happened.
+    /// Handle procreation and return the new bodies if one has happened.
     pub fn handle_procreation(
-        &mut self,
-        body_id: &BodyId,
-        new_bodies: &mut HashMap<BodyId, Self>,
-        removed_bodies: &mut HashMap<BodyId, Vec2>,
+        &self,
+        user_constants: &UserConstants,
         rng: &mut StdRng,
-    ) -> bool {
+    ) -> Option<[Self; 2]> {
         if self.energy > self.division_threshold {
-            for _ in 0..2 {
-                new_bodies.insert(
-                    Instant::now(),
-                    Body::new(
-                        self.pos,
-                        Some(self.energy),
-                        self.eating_strategy,
-                        Some(self.division_threshold),
-                        Some(self.skills.clone()),
-                        self.color,
-                        self.body_type,
-                        Some(self.viruses.clone()),
-                        Some(self.initial_speed),
-                        Some(self.initial_vision_distance),
-                        rng,
-                    ),
+            Some([(); 2].map(|_| {
+                let mut child = Body::new(
+                    self.pos,
+                    Some(self.energy),
+                    self.eating_strategy,
+                    Some(self.division_threshold),
+                    Some(self.skills),
+                    self.color,
+                    self.body_type,
+                    Some(self.viruses),
+                    Some(self.initial_speed),
+                    Some(self.initial_vision_distance),
+                    user_constants,
+                    rng,
                 );
-            }
+                child.generation = self.generation + 1;
 
-            removed_bodies.insert(*body_id, self.pos);
-
-            true
+                child
+            }))
         } else {
-            false
+            None
         }
     }
 
-    pub fn get_spent_energy(&self, time: f32) -> f32 {
-        let user_constants = USER_CONSTANTS.read().unwrap();
+    /// Decide what the body does during the tick. Only `bodies`,
+    /// `plants` and `crosses` as they were at the start of the tick are
+    /// looked at, so the order the bodies decide in doesn't matter.
+    /// `visible_bodies` is a scratch buffer reused between the bodies.
+    pub fn decide<'a>(
+        &self,
+        body_id: &BodyId,
+        bodies: &'a SpatialGrid<Body>,
+        plants: &SpatialGrid<Plant>,
+        crosses: &SpatialGrid<Cross>,
+        removed_plants: &FxHashMap<PlantId, Vec2>,
+        conditions: &[ConditionRegion],
+        user_constants: &UserConstants,
+        visible_bodies: &mut Vec<(&'a BodyId, &'a Body)>,
+        rng: &mut StdRng,
+    ) -> (Self, Action) {
+        let mut body = *self;
+        body.age += 1;
+
+        body.handle_viruses(user_constants);
+        body.handle_lifespan(user_constants);
+
+        // Handle if dead to become a cross
+        if body.energy < user_constants.min_energy
+            || body.age as f32 / SECOND_TICKS_N as f32 > body.lifespan
+        {
+            body.status = Status::Cross;
+
+            return (body, Action::Die);
+        }
+
+        if body.handle_energy(conditions, user_constants) {
+            return (body, Action::Die);
+        }
+
+        visible_bodies.clear();
+        visible_bodies.extend(
+            bodies.query_circle(body.get_vision()).filter(
+                |(other_body_id, _)| *other_body_id != body_id,
+            ),
+        );
+
+        // Escape
+        let is_chaser =
+            |other_body: &Body| other_body.is_following(body_id);
+        let only_faster_chasers = body
+            .skills
+            .contains(Skill::PrioritizeFasterChasers)
+            && visible_bodies.iter().any(|(_, other_body)| {
+                is_chaser(other_body) && other_body.speed > body.speed
+            });
+
+        if let Some((closest_chasing_body_id, closest_chasing_body)) =
+            visible_bodies
+                .iter()
+                .filter(|(_, other_body)| {
+                    is_chaser(other_body)
+                        && (!only_faster_chasers
+                            || other_body.speed > body.speed)
+                })
+                .min_by(|(_, a), (_, b)| {
+                    AREA_TOPOLOGY.distance(body.pos, a.pos).total_cmp(
+                        &AREA_TOPOLOGY.distance(body.pos, b.pos),
+                    )
+                })
+        {
+            body.status = Status::EscapingBody(
+                **closest_chasing_body_id,
+                closest_chasing_body.body_type,
+            );
+
+            let distance_to_closest_chasing_body = AREA_TOPOLOGY
+                .distance(body.pos, closest_chasing_body.pos);
+
+            body.last_pos -= AREA_TOPOLOGY
+                .delta(body.last_pos, closest_chasing_body.last_pos)
+                * (body.speed / distance_to_closest_chasing_body);
+
+            body.wrap();
+
+            return (body, Action::None);
+        }
+
+        // Eating
+        if let Some(food) = body.find_food(
+            visible_bodies,
+            plants,
+            crosses,
+            removed_plants,
+            user_constants,
+        ) {
+            let distance_to_food =
+                AREA_TOPOLOGY.distance(body.pos, food.pos);
+
+            if distance_to_food <= body.speed {
+                body.last_pos = food.pos;
+                body.status = Status::Undefined;
+
+                return (body, Action::Eat(food));
+            }
+
+            body.status = Status::FollowingTarget(
+                food.id,
+                food.pos,
+                food.food_type,
+            );
+            body.last_pos += AREA_TOPOLOGY
+                .delta(body.last_pos, food.pos)
+                * (body.speed / distance_to_food);
+
+            // The food may be across the border
+            body.wrap();
+
+            return (body, Action::None);
+        }
+
+        // Procreate
+        if let Some(children) =
+            body.handle_procreation(user_constants, rng)
+        {
+            return (body, Action::Procreate(children));
+        }
+
+        body.handle_walking_or_idle(rng);
+
+        (body, Action::None)
+    }
 
+    pub fn get_spent_energy(
+        &self,
+        time: f32,
+        user_constants: &UserConstants,
+    ) -> f32 {
         time * user_constants.energy_spent_const_for_movement
             * self.speed.powi(2)
             * self.energy
@@ -791,9 +1140,12 @@ impl Body {
 
     /// Generate a random position until it suits certain creteria.
     pub fn randomly_spawn_body(
-        bodies: &mut [Vec<HashMap<BodyId, Self>>],
-        eating_strategy: EatingStrategy,
-        body_type: usize,
+        bodies: &mut SpatialGrid<Self>,
+        species: &Species,
+        body_type: u32,
+        color: Color,
+        ids: &mut IdCounter,
+        user_constants: &UserConstants,
         rng: &mut StdRng,
     ) {
         let mut pos = Vec2::default();
@@ -806,360 +1158,246 @@ impl Body {
                 || pos.x >= AREA_SIZE.x - OBJECT_RADIUS - MIN_GAP)
                 || (pos.y <= OBJECT_RADIUS + MIN_GAP
                     || pos.y >= AREA_SIZE.y - OBJECT_RADIUS - MIN_GAP)
-                || {
-                    let Cell { i, j } = CELLS.get_cell_by_pos(pos);
-                    bodies[i][j].values().any(|body| {
-                        body.last_pos.distance(pos)
-                            < OBJECT_RADIUS * 2.0 + MIN_GAP
-                    })
-                }
+                || bodies
+                    .query_circle(Circle::new(
+                        pos.x,
+                        pos.y,
+                        OBJECT_RADIUS * 2.0 + MIN_GAP,
+                    ))
+                    .next()
+                    .is_some()
         } {}
 
-        let user_constants = USER_CONSTANTS.read().unwrap();
-
-        // Make sure the color is different enough
-        let real_color_gap = COLOR_GAP
-            / ((user_constants.omnivorous_n
-                + user_constants.herbivorous_n
-                + user_constants.carnivorous_n
-                + 3) as f32)
-                .powf(1.0 / 3.0);
-
-        let mut color = Color::from_rgba(
-            gen_range(COLOR_MIN, COLOR_MAX),
-            gen_range(COLOR_MIN, COLOR_MAX),
-            gen_range(COLOR_MIN, COLOR_MAX),
-            255,
+        let mut body = Body::new(
+            pos,
+            None,
+            species.eating_strategy,
+            species.average_division_threshold,
+            None,
+            color,
+            body_type,
+            None,
+            species.average_speed,
+            species.average_vision_distance,
+            user_constants,
+            rng,
         );
 
-        let green_rgb = vec3(GREEN.r, GREEN.g, GREEN.b);
-
-        let red_rgb = vec3(RED.r, RED.g, RED.b);
+        body.skills = species.skills.iter().copied().collect();
 
-        while bodies.iter().any(|row| {
-            row.iter().any(|column| {
-                column.values().any(|body| {
-                    let current_body_rgb = vec3(
-                        body.color.r,
-                        body.color.g,
-                        body.color.b,
-                    );
-                    current_body_rgb.distance(green_rgb)
-                        < real_color_gap
-                        || current_body_rgb.distance(red_rgb)
-                            < real_color_gap
-                        || current_body_rgb
-                            .distance(vec3(color.r, color.g, color.b))
-                            < real_color_gap
-                })
-            })
-        }) {
-            color = Color::from_rgba(
-                gen_range(COLOR_MIN, COLOR_MAX),
-                gen_range(COLOR_MIN, COLOR_MAX),
-                gen_range(COLOR_MIN, COLOR_MAX),
-                255,
-            )
+        if let Some(average_energy) = species.average_energy {
+            body.energy = get_with_deviation(
+                average_energy,
+                user_constants,
+                rng,
+            );
         }
 
-        let Cell { i, j } = CELLS.get_cell_by_pos(pos);
-
-        bodies[i][j].insert(
-            Instant::now(),
-            Body::new(
-                pos,
-                None,
-                eating_strategy,
-                None,
-                None,
-                color,
-                body_type as u32,
-                None,
-                None,
-                None,
-                rng,
-            ),
-        );
+        bodies.insert(ids.next_id(), body);
     }
 
     #[inline(always)]
-    pub fn find_food<'a>(
+    pub fn find_food(
         &mut self,
-        body_id: &BodyId,
-        bodies: &'a [Vec<HashMap<BodyId, Body>>],
-        plants: &'a [Vec<HashMap<PlantId, Plant>>],
-        crosses: &'a [Vec<HashMap<CrossId, Cross>>],
-        removed_bodies: &HashMap<Instant, Vec2>,
-        removed_plants: &HashMap<Instant, Vec2>,
-    ) -> Option<FoodInfo<'a>> {
-        let mut visible_crosses = HashMap::new();
+        visible_bodies: &[(&BodyId, &Body)],
+        plants: &SpatialGrid<Plant>,
+        crosses: &SpatialGrid<Cross>,
+        removed_plants: &FxHashMap<PlantId, Vec2>,
+        user_constants: &UserConstants,
+    ) -> Option<FoodInfo> {
+        let vision = self.get_vision();
 
+        // Find the closest cross
         if let EatingStrategy::Omnivorous
         | EatingStrategy::Carnivorous = self.eating_strategy
         {
-            get_visible!(self, crosses, visible_crosses);
-        }
-
-        // Find the closest plant
-        let mut visible_bodies = HashMap::new();
-
-        get_visible!(self, bodies, visible_bodies);
-
-        visible_bodies.remove(body_id);
-
-        let filtered_visible_bodies = visible_bodies
-            .iter()
-            .filter(|(other_body_id, _)| {
-                !removed_bodies.contains_key(other_body_id)
-            })
-            .collect::<HashMap<_, _>>();
-
-        let visible_bodies_of_my_type = filtered_visible_bodies
-            .iter()
-            .filter(|(_, other_body)| {
-                other_body.body_type == self.body_type
-            })
-            .collect::<HashMap<_, _>>();
-
-        let closest_cross = visible_crosses
-            .iter()
-            .filter(|(cross_id, cross)| {
-                let same_target_visible_bodies =
-                    filtered_visible_bodies
-                        .iter()
-                        .filter(|(_, other_body)| {
-                            if let Status::FollowingTarget(
-                                other_body_target_id,
-                                _,
-                                _,
-                            ) = other_body.status
-                            {
-                                &&&other_body_target_id == cross_id
-                            } else {
-                                false
-                            }
-                        })
-                        .collect::<HashMap<_, _>>();
-
-                self.handle_eat_crosses_of_my_type(cross)
-                    && self.handle_alive_when_arrived_cross(cross)
-                    && self
-                        .handle_profitable_when_arrived_cross(cross)
-                    && self.handle_avoid_new_viruses_cross(cross)
-                    && self.handle_will_arrive_first_cross(
-                        cross,
-                        &same_target_visible_bodies,
-                    )
-                    && self.handle_do_not_compete_with_relatives(
-                        cross_id,
-                        &visible_bodies_of_my_type,
-                    )
-                    && self
-                        .handle_do_not_compete_with_younger_relatives(
+            let closest_cross = crosses
+                .query_circle(vision)
+                .filter(|(cross_id, cross)| {
+                    self.handle_eat_crosses_of_my_type(cross)
+                        && self.handle_alive_when_arrived_cross(
+                            cross,
+                            user_constants,
+                        )
+                        && self.handle_profitable_when_arrived_cross(
+                            cross,
+                            user_constants,
+                        )
+                        && self.handle_avoid_new_viruses_cross(cross)
+                        && self.handle_will_arrive_first_cross(
                             cross_id,
-                            &visible_bodies_of_my_type,
+                            cross,
+                            visible_bodies,
                         )
-            })
-            .min_by(|(_, a), (_, b)| {
-                self.pos
-                    .distance(a.pos)
-                    .partial_cmp(&self.pos.distance(b.pos))
-                    .unwrap()
-            });
+                        && self.handle_do_not_compete_with_relatives(
+                            cross_id,
+                            visible_bodies,
+                        )
+                        && self
+                            .handle_do_not_compete_with_younger_relatives(
+                                cross_id,
+                                visible_bodies,
+                            )
+                })
+                .min_by(|(_, a), (_, b)| {
+                    AREA_TOPOLOGY
+                        .distance(self.pos, a.pos)
+                        .partial_cmp(
+                            &AREA_TOPOLOGY.distance(self.pos, b.pos),
+                        )
+                        .unwrap()
+                });
 
-        // Find the closest cross
-        match closest_cross {
-            Some((closest_cross_id, closest_cross)) => {
+            if let Some((closest_cross_id, closest_cross)) =
+                closest_cross
+            {
                 return Some(FoodInfo {
-                    id:        **closest_cross_id,
+                    id:        *closest_cross_id,
                     food_type: ObjectType::Cross,
                     pos:       closest_cross.pos,
                     energy:    closest_cross.energy,
-                    viruses:   Some(&closest_cross.viruses),
-                })
+                    viruses:   Some(closest_cross.viruses),
+                });
             }
-            None => {
-                let mut visible_plants = HashMap::new();
+        }
 
-                if let EatingStrategy::Omnivorous
-                | EatingStrategy::Herbivorous =
-                    self.eating_strategy
+        // Find the closest plant, preferring bananas to grass
+        if let EatingStrategy::Omnivorous
+        | EatingStrategy::Herbivorous = self.eating_strategy
+        {
+            let mut closest_banana: Option<(&PlantId, &Plant)> = None;
+            let mut closest_grass: Option<(&PlantId, &Plant)> = None;
+
+            for (plant_id, plant) in plants.query_circle(vision) {
+                if removed_plants.contains_key(plant_id)
+                    || !self.handle_alive_when_arrived_plant(
+                        plant,
+                        user_constants,
+                    )
+                    || !self.handle_profitable_when_arrived_plant(
+                        plant,
+                        user_constants,
+                    )
+                    || !self.handle_do_not_compete_with_relatives(
+                        plant_id,
+                        visible_bodies,
+                    )
+                    || !self
+                        .handle_do_not_compete_with_younger_relatives(
+                            plant_id,
+                            visible_bodies,
+                        )
+                    || !self.handle_will_arrive_first_plant(
+                        plant_id,
+                        plant,
+                        visible_bodies,
+                    )
                 {
-                    get_visible!(self, plants, visible_plants);
+                    continue;
                 }
 
-                let filtered_visible_plants = visible_plants
-                    .iter()
-                    .filter(|(plant_id, plant)| {
-                        let same_target_visible_bodies =
-                        filtered_visible_bodies
-                            .iter()
-                            .filter(|(_, other_body)| {
-                                if let Status::FollowingTarget(
-                                    other_body_target_id,
-                                    _,
-                                    _,
-                                ) = other_body.status
-                                {
-                                    &&&other_body_target_id == plant_id
-                                } else {
-                                    false
-                                }
-                            })
-                            .collect::<HashMap<_, _>>();
+                let closest_plant = match plant.kind {
+                    PlantKind::Banana => &mut closest_banana,
+                    PlantKind::Grass => &mut closest_grass,
+                };
+
+                if closest_plant.is_none_or(|(_, closest_plant)| {
+                    AREA_TOPOLOGY.distance(self.pos, plant.pos)
+                        < AREA_TOPOLOGY
+                            .distance(self.pos, closest_plant.pos)
+                }) {
+                    *closest_plant = Some((plant_id, plant));
+                }
+            }
 
-                        !removed_plants.contains_key(plant_id)
-                        && self.handle_alive_when_arrived_plant(plant)
-                        && self.handle_profitable_when_arrived_plant(plant)
-                        && self.handle_do_not_compete_with_relatives(
-                            plant_id,
-                            &visible_bodies_of_my_type
+            if let Some((closest_plant_id, closest_plant)) =
+                closest_banana.or(closest_grass)
+            {
+                return Some(FoodInfo {
+                    id:        *closest_plant_id,
+                    food_type: ObjectType::Plant,
+                    pos:       closest_plant.pos,
+                    energy:    closest_plant.get_contained_energy(),
+                    viruses:   None,
+                });
+            }
+        }
+
+        if let EatingStrategy::Carnivorous = self.eating_strategy {
+            self.spend_energy_on_vision =
+                visible_bodies.iter().any(|(_, other_body)| {
+                    other_body.body_type != self.body_type
+                });
+        }
+
+        // Find the closest body
+        if let EatingStrategy::Omnivorous
+        | EatingStrategy::Carnivorous = self.eating_strategy
+        {
+            let closest_body = visible_bodies
+                .iter()
+                .filter(|(other_body_id, other_body)| {
+                    self.body_type != other_body.body_type
+                        && (match self.eating_strategy {
+                            EatingStrategy::Carnivorous => {
+                                self.energy > match other_body.eating_strategy {
+                                    EatingStrategy::Carnivorous => other_body.energy,
+                                    EatingStrategy::Herbivorous
+                                    | EatingStrategy::Omnivorous => other_body.energy * user_constants.carnivorous_energy_const
+                                }
+                            }
+                            EatingStrategy::Omnivorous => {
+                                other_body.energy < match other_body.eating_strategy {
+                                    EatingStrategy::Carnivorous => self.energy * user_constants.carnivorous_energy_const,
+                                    EatingStrategy::Herbivorous
+                                    | EatingStrategy::Omnivorous => self.energy
+                                }
+                            }
+                            _ => unreachable!()
+                        })
+                        && self.handle_alive_when_arrived_body(
+                            other_body,
+                            user_constants,
                         )
-                        && self.handle_do_not_compete_with_younger_relatives(
-                            plant_id,
-                            &visible_bodies_of_my_type
+                        && self.handle_profitable_when_arrived_body(
+                            other_body,
+                            user_constants,
                         )
-                        && self.handle_will_arrive_first_plant(
-                            plant,
-                            &same_target_visible_bodies
+                        && self.handle_avoid_new_viruses_body(other_body)
+                        && self.handle_will_arrive_first_body(
+                            other_body_id,
+                            other_body,
+                            visible_bodies,
                         )
-                    }).collect::<Vec<_>>();
-
-                match self
-                    .find_closest_plant(
-                        &filtered_visible_plants,
-                        PlantKind::Banana,
-                    )
-                    .or_else(|| {
-                        self.find_closest_plant(
-                            &filtered_visible_plants,
-                            PlantKind::Grass,
+                        && self.handle_do_not_compete_with_relatives(
+                            other_body_id,
+                            visible_bodies,
                         )
-                    }) {
-                    Some((closest_plant_id, closest_plant)) => {
-                        return Some(FoodInfo {
-                            id:        ***closest_plant_id,
-                            food_type: ObjectType::Plant,
-                            pos:       closest_plant.pos,
-                            energy:    closest_plant
-                                .get_contained_energy(),
-                            viruses:   None,
-                        })
-                    }
-                    None => {
-                        if let EatingStrategy::Carnivorous =
-                            self.eating_strategy
-                        {
-                            self.spend_energy_on_vision =
-                                visible_bodies.values().any(
-                                    |other_body| {
-                                        other_body.body_type
-                                            != self.body_type
-                                    },
-                                );
-                        }
-
-                        let user_constants =
-                            USER_CONSTANTS.read().unwrap();
+                        && self
+                            .handle_do_not_compete_with_younger_relatives(
+                                other_body_id,
+                                visible_bodies,
+                            )
+                })
+                .min_by(|(_, a), (_, b)| {
+                    AREA_TOPOLOGY
+                        .distance(self.pos, a.pos)
+                        .partial_cmp(
+                            &AREA_TOPOLOGY.distance(self.pos, b.pos),
+                        )
+                        .unwrap()
+                });
 
-                        // Find the closest plant
-                        if let EatingStrategy::Omnivorous
-                        | EatingStrategy::Carnivorous =
-                            self.eating_strategy
-                        {
-                            let visible_bodies_of_other_types =
-                                filtered_visible_bodies
-                                    .iter()
-                                    .filter(|(_, other_body)| {
-                                        self.body_type
-                                            != other_body.body_type
-                                    })
-                                    .collect::<HashMap<_, _>>();
-
-                            let closest_body = visible_bodies_of_other_types
-                                .iter()
-                                .filter(|(other_body_id, other_body)| {
-                                    let same_target_visible_bodies =
-                                    filtered_visible_bodies
-                                        .iter()
-                                        .filter(|(_, other_body)| {
-                                            if let Status::FollowingTarget(
-                                                other_body_target_id,
-                                                _,
-                                                _,
-                                            ) = other_body.status
-                                            {
-                                                &&&&&other_body_target_id == other_body_id
-                                            } else {
-                                                false
-                                            }
-                                        })
-                                        .collect::<HashMap<_, _>>();
-
-                                    (match self.eating_strategy {
-                                        EatingStrategy::Carnivorous => {
-                                            self.energy > match other_body.eating_strategy {
-                                                EatingStrategy::Carnivorous => other_body.energy,
-                                                EatingStrategy::Herbivorous
-                                                | EatingStrategy::Omnivorous => other_body.energy * user_constants.carnivorous_energy_const
-                                            }
-                                        }
-                                        EatingStrategy::Omnivorous => {
-                                            other_body.energy < match other_body.eating_strategy {
-                                                EatingStrategy::Carnivorous => self.energy * user_constants.carnivorous_energy_const ,
-                                                EatingStrategy::Herbivorous
-                                                | EatingStrategy::Omnivorous => self.energy
-                                            }
-                                        }
-                                        _ => unreachable!()
-                                    })
-                                    && self.handle_alive_when_arrived_body(
-                                        other_body,
-                                    )
-                                    && self.handle_profitable_when_arrived_body(
-                                        other_body,
-                                    )
-                                    && self.handle_avoid_new_viruses_body(other_body)
-                                    && self.handle_will_arrive_first_body(
-                                        other_body,
-                                        &same_target_visible_bodies
-                                    )
-                                    && self.handle_do_not_compete_with_relatives(
-                                        other_body_id,
-                                        &visible_bodies_of_my_type
-
-                                    )
-                                    && self.handle_do_not_compete_with_younger_relatives(
-                                        other_body_id,
-                                        &visible_bodies_of_my_type
-                                    )
-                                })
-                                .min_by(|(_, a), (_, b)| {
-                                    self.pos
-                                        .distance(a.pos)
-                                        .partial_cmp(&self.pos.distance(b.pos))
-                                        .unwrap()
-                                });
-
-                            // Find the closest body
-                            if let Some((
-                                closest_body_id,
-                                closest_body,
-                            )) = closest_body
-                            {
-                                return Some(FoodInfo {
-                                    id:        ****closest_body_id,
-                                    food_type: ObjectType::Body,
-                                    pos:       closest_body.pos,
-                                    energy:    closest_body.energy,
-                                    viruses:   Some(
-                                        &closest_body.viruses,
-                                    ),
-                                });
-                            }
-                        }
-                    }
-                }
+            if let Some((closest_body_id, closest_body)) =
+                closest_body
+            {
+                return Some(FoodInfo {
+                    id:        **closest_body_id,
+                    food_type: ObjectType::Body,
+                    pos:       closest_body.pos,
+                    energy:    closest_body.energy,
+                    viruses:   Some(closest_body.viruses),
+                });
             }
         }
 
@@ -1167,28 +1405,22 @@ impl Body {
     }
 
     #[inline(always)]
-    pub fn find_closest_plant<'a>(
-        &self,
-        visible_plants: &'a [(&&PlantId, &&Plant)],
-        plant_kind: PlantKind,
-    ) -> Option<&'a (&&'a PlantId, &&Plant)> {
-        visible_plants
-            .iter()
-            .filter(|(_, plant)| plant.kind == plant_kind)
-            .min_by(|(_, a), (_, b)| {
-                self.pos
-                    .distance(a.pos)
-                    .partial_cmp(&self.pos.distance(b.pos))
-                    .unwrap()
-            })
+    /// Whether the body is heading for the object.
+    pub fn is_following(&self, target_id: &ObjectId) -> bool {
+        matches!(
+            self.status,
+            Status::FollowingTarget(other_target_id, _, _)
+                if &other_target_id == target_id
+        )
     }
 
     #[inline(always)]
     pub fn handle_profitable_when_arrived_body(
         &self,
         other_body: &Body,
+        user_constants: &UserConstants,
     ) -> bool {
-        if self.skills.contains(&Skill::ProfitableWhenArrived) {
+        if self.skills.contains(Skill::ProfitableWhenArrived) {
             let divisor = self.speed - other_body.speed;
 
             if divisor <= 0.0 {
@@ -1196,7 +1428,9 @@ impl Body {
             }
 
             self.get_spent_energy(
-                self.pos.distance(other_body.pos) / divisor,
+                AREA_TOPOLOGY.distance(self.pos, other_body.pos)
+                    / divisor,
+                user_constants,
             ) < other_body.energy
         } else {
             true
@@ -1207,10 +1441,13 @@ impl Body {
     pub fn handle_profitable_when_arrived_plant(
         &self,
         plant: &Plant,
+        user_constants: &UserConstants,
     ) -> bool {
-        if self.skills.contains(&Skill::ProfitableWhenArrived) {
+        if self.skills.contains(Skill::ProfitableWhenArrived) {
             self.get_spent_energy(
-                self.pos.distance(plant.pos) / self.speed,
+                AREA_TOPOLOGY.distance(self.pos, plant.pos)
+                    / self.speed,
+                user_constants,
             ) < plant.get_contained_energy()
         } else {
             true
@@ -1221,10 +1458,13 @@ impl Body {
     pub fn handle_profitable_when_arrived_cross(
         &self,
         cross: &Cross,
+        user_constants: &UserConstants,
     ) -> bool {
-        if self.skills.contains(&Skill::ProfitableWhenArrived) {
+        if self.skills.contains(Skill::ProfitableWhenArrived) {
             self.get_spent_energy(
-                self.pos.distance(cross.pos) / self.speed,
+                AREA_TOPOLOGY.distance(self.pos, cross.pos)
+                    / self.speed,
+                user_constants,
             ) < cross.energy
         } else {
             true
@@ -1235,13 +1475,14 @@ impl Body {
     pub fn handle_alive_when_arrived_cross(
         &self,
         cross: &Cross,
+        user_constants: &UserConstants,
     ) -> bool {
-        let user_constants = USER_CONSTANTS.read().unwrap();
-
-        if self.skills.contains(&Skill::AliveWhenArrived) {
+        if self.skills.contains(Skill::AliveWhenArrived) {
             self.energy
                 - self.get_spent_energy(
-                    self.pos.distance(cross.pos) / self.speed,
+                    AREA_TOPOLOGY.distance(self.pos, cross.pos)
+                        / self.speed,
+                    user_constants,
                 )
                 > user_constants.min_energy
         } else {
@@ -1253,10 +1494,9 @@ impl Body {
     pub fn handle_alive_when_arrived_body(
         &self,
         other_body: &Self,
+        user_constants: &UserConstants,
     ) -> bool {
-        let user_constants = USER_CONSTANTS.read().unwrap();
-
-        if self.skills.contains(&Skill::AliveWhenArrived) {
+        if self.skills.contains(Skill::AliveWhenArrived) {
             let divisor = self.speed - other_body.speed;
 
             if divisor <= 0.0 {
@@ -1265,7 +1505,9 @@ impl Body {
 
             self.energy
                 - self.get_spent_energy(
-                    self.pos.distance(other_body.pos) / divisor,
+                    AREA_TOPOLOGY.distance(self.pos, other_body.pos)
+                        / divisor,
+                    user_constants,
                 )
                 > user_constants.min_energy
         } else {
@@ -1277,13 +1519,14 @@ impl Body {
     pub fn handle_alive_when_arrived_plant(
         &self,
         plant: &Plant,
+        user_constants: &UserConstants,
     ) -> bool {
-        let user_constants = USER_CONSTANTS.read().unwrap();
-
-        if self.skills.contains(&Skill::AliveWhenArrived) {
+        if self.skills.contains(Skill::AliveWhenArrived) {
             self.energy
                 - self.get_spent_energy(
-                    self.pos.distance(plant.pos) / self.speed,
+                    AREA_TOPOLOGY.distance(self.pos, plant.pos)
+                        / self.speed,
+                    user_constants,
                 )
                 > user_constants.min_energy
         } else {
@@ -1296,11 +1539,11 @@ impl Body {
         &self,
         cross: &Cross,
     ) -> bool {
-        if self.skills.contains(&Skill::AvoidNewViruses) {
+        if self.skills.contains(Skill::AvoidNewViruses) {
             cross
                 .viruses
                 .keys()
-                .all(|virus| self.viruses.contains_key(virus))
+                .all(|virus| self.viruses.contains(virus))
         } else {
             true
         }
@@ -1311,11 +1554,11 @@ impl Body {
         &self,
         other_body: &Self,
     ) -> bool {
-        if self.skills.contains(&Skill::AvoidNewViruses) {
+        if self.skills.contains(Skill::AvoidNewViruses) {
             other_body
                 .viruses
                 .keys()
-                .all(|virus| self.viruses.contains_key(virus))
+                .all(|virus| self.viruses.contains(virus))
         } else {
             true
         }
@@ -1324,21 +1567,13 @@ impl Body {
     #[inline(always)]
     pub fn handle_do_not_compete_with_relatives(
         &self,
-        target_id: &Instant,
-        visible_bodies_of_my_type: &HashMap<&&&BodyId, &&&Self>,
+        target_id: &ObjectId,
+        visible_bodies: &[(&BodyId, &Self)],
     ) -> bool {
-        if self.skills.contains(&Skill::DoNotCompeteWithRelatives) {
-            visible_bodies_of_my_type.iter().all(|(_, other_body)| {
-                if let Status::FollowingTarget(
-                    other_body_target_id,
-                    _,
-                    _,
-                ) = other_body.status
-                {
-                    &other_body_target_id != target_id
-                } else {
-                    true
-                }
+        if self.skills.contains(Skill::DoNotCompeteWithRelatives) {
+            visible_bodies.iter().all(|(_, other_body)| {
+                other_body.body_type != self.body_type
+                    || !other_body.is_following(target_id)
             })
         } else {
             true
@@ -1348,28 +1583,17 @@ impl Body {
     #[inline(always)]
     pub fn handle_do_not_compete_with_younger_relatives(
         &self,
-        target_id: &Instant,
-        visible_bodies_of_my_type: &HashMap<&&&BodyId, &&&Self>,
+        target_id: &ObjectId,
+        visible_bodies: &[(&BodyId, &Self)],
     ) -> bool {
         if self
             .skills
-            .contains(&Skill::DoNotCompeteWithYoungerRelatives)
+            .contains(Skill::DoNotCompeteWithYoungerRelatives)
         {
-            visible_bodies_of_my_type.iter().all(|(_, relative)| {
-                if let Status::FollowingTarget(
-                    relative_target_id,
-                    _,
-                    _,
-                ) = relative.status
-                {
-                    if &relative_target_id == target_id {
-                        relative.lifespan < self.lifespan
-                    } else {
-                        true
-                    }
-                } else {
-                    true
-                }
+            visible_bodies.iter().all(|(_, relative)| {
+                relative.body_type != self.body_type
+                    || !relative.is_following(target_id)
+                    || relative.lifespan < self.lifespan
             })
         } else {
             true
@@ -1379,18 +1603,24 @@ impl Body {
     #[inline(always)]
     pub fn handle_will_arrive_first_cross(
         &self,
+        cross_id: &CrossId,
         cross: &Cross,
-        same_target_visible_bodies: &HashMap<&&&BodyId, &&&Self>,
+        visible_bodies: &[(&BodyId, &Self)],
     ) -> bool {
-        if self.skills.contains(&Skill::WillArriveFirst) {
-            let time = self.pos.distance(cross.pos) / self.speed;
-
-            same_target_visible_bodies.iter().all(
-                |(_, other_body)| {
-                    time < other_body.pos.distance(cross.pos)
+        if self.skills.contains(Skill::WillArriveFirst) {
+            let time = AREA_TOPOLOGY.distance(self.pos, cross.pos)
+                / self.speed;
+
+            visible_bodies
+                .iter()
+                .filter(|(_, other_body)| {
+                    other_body.is_following(cross_id)
+                })
+                .all(|(_, other_body)| {
+                    time < AREA_TOPOLOGY
+                        .distance(other_body.pos, cross.pos)
                         / other_body.speed
-                },
-            )
+                })
         } else {
             true
         }
@@ -1399,31 +1629,37 @@ impl Body {
     #[inline(always)]
     pub fn handle_will_arrive_first_body(
         &self,
+        other_body_id: &BodyId,
         other_body: &Self,
-        same_target_visible_bodies: &HashMap<&&&BodyId, &&&Self>,
+        visible_bodies: &[(&BodyId, &Self)],
     ) -> bool {
-        if self.skills.contains(&Skill::WillArriveFirst) {
+        if self.skills.contains(Skill::WillArriveFirst) {
             let delta = self.speed - other_body.speed;
             if delta <= 0.0 {
                 return false;
             }
 
-            let time = self.pos.distance(other_body.pos) / delta;
-            same_target_visible_bodies.iter().all(
-                |(_, other_chaser)| {
+            let time = AREA_TOPOLOGY
+                .distance(self.pos, other_body.pos)
+                / delta;
+            visible_bodies
+                .iter()
+                .filter(|(_, other_chaser)| {
+                    other_chaser.is_following(other_body_id)
+                })
+                .all(|(_, other_chaser)| {
                     let chaser_delta =
                         other_chaser.speed - other_body.speed;
 
                     if chaser_delta > 0.0 {
-                        time < other_chaser
-                            .pos
-                            .distance(other_body.pos)
-                            / chaser_delta
+                        time < AREA_TOPOLOGY.distance(
+                            other_chaser.pos,
+                            other_body.pos,
+                        ) / chaser_delta
                     } else {
                         true
                     }
-                },
-            )
+                })
         } else {
             true
         }
@@ -1432,18 +1668,24 @@ impl Body {
     #[inline(always)]
     pub fn handle_will_arrive_first_plant(
         &self,
+        plant_id: &PlantId,
         plant: &Plant,
-        same_target_visible_bodies: &HashMap<&&&BodyId, &&&Self>,
+        visible_bodies: &[(&BodyId, &Self)],
     ) -> bool {
-        if self.skills.contains(&Skill::WillArriveFirst) {
-            let time = self.pos.distance(plant.pos) / self.speed;
-
-            same_target_visible_bodies.iter().all(
-                |(_, other_body)| {
-                    time < other_body.pos.distance(plant.pos)
+        if self.skills.contains(Skill::WillArriveFirst) {
+            let time = AREA_TOPOLOGY.distance(self.pos, plant.pos)
+                / self.speed;
+
+            visible_bodies
+                .iter()
+                .filter(|(_, other_body)| {
+                    other_body.is_following(plant_id)
+                })
+                .all(|(_, other_body)| {
+                    time < AREA_TOPOLOGY
+                        .distance(other_body.pos, plant.pos)
                         / other_body.speed
-                },
-            )
+                })
         } else {
             true
         }
@@ -1455,6 +1697,6 @@ impl Body {
         cross: &Cross,
     ) -> bool {
         self.body_type != cross.body_type
-            || self.skills.contains(&Skill::EatCrossesOfMyType)
+            || self.skills.contains(Skill::EatCrossesOfMyType)
     }
 }
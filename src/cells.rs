@@ -1,4 +1,30 @@
-use macroquad::math::Vec2;
+use crate::{AREA_SIZE, CELLS};
+use macroquad::math::{vec2, Circle, Rect, Vec2};
+use rand::{rngs::StdRng, seq::SliceRandom};
+use rayon::prelude::*;
+use rustc_hash::FxHashMap;
+use std::{collections::HashSet, ops::RangeInclusive, vec::Drain};
+
+/// The ID of a body, a plant or a cross. The IDs are given out in order,
+/// so an older object has a lower ID.
+pub type ObjectId = u64;
+
+#[derive(Default)]
+/// Gives out the IDs, so that they're the same for every run with the
+/// same seed.
+pub struct IdCounter {
+    next: ObjectId,
+}
+
+impl IdCounter {
+    #[inline(always)]
+    pub fn next_id(&mut self) -> ObjectId {
+        let id = self.next;
+        self.next += 1;
+
+        id
+    }
+}
 
 #[derive(Eq, Hash, PartialEq, Clone)]
 pub struct Cell {
@@ -25,3 +51,680 @@ impl Cells {
         }
     }
 }
+
+/// Anything that can be stored in a `SpatialGrid`.
+pub trait Positioned {
+    fn pos(&self) -> Vec2;
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// How the borders of the area are treated by the queries.
+pub enum Topology {
+    /// Nothing can be found beyond the borders.
+    Bounded,
+    /// The opposite borders are glued together.
+    Toroidal,
+}
+
+impl Topology {
+    #[inline(always)]
+    /// The shortest shift from `from` to `to`.
+    pub fn delta(&self, from: Vec2, to: Vec2) -> Vec2 {
+        let delta = to - from;
+
+        match self {
+            Topology::Bounded => delta,
+            Topology::Toroidal => {
+                delta - (delta / *AREA_SIZE).round() * *AREA_SIZE
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub fn distance(&self, a: Vec2, b: Vec2) -> f32 {
+        self.delta(a, b).length()
+    }
+
+    #[inline(always)]
+    /// Get the rows and columns of the query that aren't cut off by the
+    /// borders in the bounded topology.
+    fn clamp_range(
+        &self,
+        min: f32,
+        max: f32,
+        n: usize,
+    ) -> RangeInclusive<isize> {
+        match self {
+            Topology::Bounded => {
+                (min.floor().max(0.0) as isize)
+                    ..=(max.floor().min((n - 1) as f32) as isize)
+            }
+            Topology::Toroidal => {
+                (min.floor() as isize)..=(max.floor() as isize)
+            }
+        }
+    }
+
+    #[inline(always)]
+    /// Get the row or column the unwrapped one corresponds to.
+    fn wrap_index(&self, index: isize, n: usize) -> Option<usize> {
+        match self {
+            Topology::Bounded => (0..n as isize)
+                .contains(&index)
+                .then_some(index as usize),
+            Topology::Toroidal => {
+                Some(index.rem_euclid(n as isize) as usize)
+            }
+        }
+    }
+}
+
+/// The bodies come out of the opposite border when they cross one, so
+/// everything is looked for and moved to through the borders.
+pub const AREA_TOPOLOGY: Topology = Topology::Toroidal;
+
+/// A cell touched by a query.
+struct QueriedCell {
+    i:             usize,
+    j:             usize,
+    /// Whether everything within the cell matches the query.
+    fully_covered: bool,
+    /// What has to be added to the positions of the objects within
+    /// the cell when it's been reached through the borders.
+    offset:        Vec2,
+}
+
+/// Objects split into the cells of `CELLS` by their positions, so
+/// that only the cells around a position have to be looked through.
+pub struct SpatialGrid<T> {
+    cells:         Vec<Vec<FxHashMap<ObjectId, T>>>,
+    /// The IDs and the positions of all the objects packed together, so
+    /// that a random object can be picked in O(1).
+    dense:         Vec<(ObjectId, Vec2)>,
+    /// Where the objects are within `dense`.
+    dense_indices: FxHashMap<ObjectId, usize>,
+    /// The objects added (`true`) and removed (`false`) since the
+    /// changes have been drained last, so that what's made of the
+    /// objects only has to be updated. `None` until they're drained for
+    /// the first time, after `for_each_in_circle_mut` and when there
+    /// have been more changes than objects, as then what's made of
+    /// them has to be remade.
+    changes:       Option<Vec<(T, bool)>>,
+    topology:      Topology,
+}
+
+impl<T: Positioned + Clone> SpatialGrid<T> {
+    pub fn new(topology: Topology) -> Self {
+        Self {
+            cells: (0..CELLS.rows)
+                .map(|_| {
+                    (0..CELLS.columns)
+                        .map(|_| FxHashMap::default())
+                        .collect()
+                })
+                .collect(),
+            dense: Vec::new(),
+            dense_indices: FxHashMap::default(),
+            changes: None,
+            topology,
+        }
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    #[inline(always)]
+    fn record_change(&mut self, object: &T, is_added: bool) {
+        if let Some(changes) = &mut self.changes {
+            if changes.len() < self.dense.len() {
+                changes.push((object.clone(), is_added));
+            } else {
+                self.changes = None;
+            }
+        }
+    }
+
+    /// Take the changes made since the last call. `None` means they
+    /// haven't been tracked and everything has to be looked through,
+    /// and they're tracked from then on.
+    pub fn drain_changes(&mut self) -> Option<Drain<'_, (T, bool)>> {
+        if self.changes.is_none() {
+            self.changes = Some(Vec::new());
+
+            return None;
+        }
+
+        self.changes.as_mut().map(|changes| changes.drain(..))
+    }
+
+    #[inline(always)]
+    /// Put the object to the cell of its position.
+    pub fn insert(&mut self, id: ObjectId, object: T) -> Option<T> {
+        let pos = object.pos();
+        let Cell { i, j } = CELLS.get_cell_by_pos(pos);
+
+        self.record_change(&object, true);
+        let previous = self.cells[i][j].insert(id, object);
+
+        if let Some(previous) = &previous {
+            self.record_change(previous, false);
+        }
+
+        match self.dense_indices.get(&id) {
+            Some(index) => self.dense[*index].1 = pos,
+            None => {
+                self.dense_indices.insert(id, self.dense.len());
+                self.dense.push((id, pos));
+            }
+        }
+
+        previous
+    }
+
+    #[inline(always)]
+    pub fn get(&self, id: &ObjectId) -> Option<&T> {
+        let (_, pos) = self.dense[*self.dense_indices.get(id)?];
+        let Cell { i, j } = CELLS.get_cell_by_pos(pos);
+
+        self.cells[i][j].get(id)
+    }
+
+    #[inline(always)]
+    /// Remove the object from the cell of `pos`.
+    pub fn remove(&mut self, id: &ObjectId, pos: Vec2) -> Option<T> {
+        let Cell { i, j } = CELLS.get_cell_by_pos(pos);
+
+        let removed = self.cells[i][j].remove(id);
+
+        if let Some(removed) = &removed {
+            self.remove_dense(id);
+            self.record_change(removed, false);
+        }
+
+        removed
+    }
+
+    #[inline(always)]
+    /// Remove the object from `dense`, putting the last one in its place.
+    fn remove_dense(&mut self, id: &ObjectId) {
+        let index = self.dense_indices.remove(id).unwrap();
+
+        self.dense.swap_remove(index);
+
+        if let Some((moved_id, _)) = self.dense.get(index) {
+            self.dense_indices.insert(*moved_id, index);
+        }
+    }
+
+    #[inline(always)]
+    /// Pick `amount` distinct random objects.
+    pub fn choose_multiple<'a>(
+        &'a self,
+        rng: &mut StdRng,
+        amount: usize,
+    ) -> impl Iterator<Item = (&'a ObjectId, &'a T)> {
+        self.dense.choose_multiple(rng, amount).map(|(id, pos)| {
+            let Cell { i, j } = CELLS.get_cell_by_pos(*pos);
+
+            self.cells[i][j].get_key_value(id).unwrap()
+        })
+    }
+
+    #[inline(always)]
+    /// Apply `f` to the object at `old_pos` and move the object to the
+    /// cell of the position `f` has given it.
+    pub fn relocate(
+        &mut self,
+        id: &ObjectId,
+        old_pos: Vec2,
+        f: impl FnOnce(&mut T),
+    ) {
+        if let Some(mut object) = self.remove(id, old_pos) {
+            f(&mut object);
+            self.insert(*id, object);
+        }
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> impl Iterator<Item = (&ObjectId, &T)> {
+        self.cells.iter().flatten().flatten()
+    }
+
+    #[inline(always)]
+    /// Iterate over the objects on multiple threads, one row of the
+    /// cells at a time.
+    pub fn par_iter(
+        &self,
+    ) -> impl ParallelIterator<Item = (&ObjectId, &T)>
+    where
+        T: Sync,
+    {
+        self.cells
+            .par_iter()
+            .flat_map_iter(|row| row.iter().flatten())
+    }
+
+    #[inline(always)]
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.iter().map(|(_, object)| object)
+    }
+
+    #[inline(always)]
+    pub fn retain(
+        &mut self,
+        mut f: impl FnMut(&ObjectId, &mut T) -> bool,
+    ) {
+        let mut removed = Vec::new();
+
+        for column in self.cells.iter_mut().flatten() {
+            column.retain(|id, object| {
+                f(id, object) || {
+                    removed.push((*id, object.clone()));
+                    false
+                }
+            });
+        }
+
+        for (id, object) in &removed {
+            self.remove_dense(id);
+            self.record_change(object, false);
+        }
+    }
+
+    /// Get the cells the circle covers or touches. Some of the cells
+    /// within the rectangle around the circle are ditched. The cells are
+    /// made on the go, so that the queries don't allocate.
+    fn cells_in_circle(
+        topology: Topology,
+        circle: Circle,
+    ) -> impl Iterator<Item = QueriedCell> {
+        // Using these for ease of development
+        let (a, b) = (circle.x, circle.y);
+        let r = circle.r;
+        let (w, h) = (CELLS.cell_width, CELLS.cell_height);
+        let (m, n) = (CELLS.columns, CELLS.rows);
+
+        let circle_center_i = (b / h).floor() as isize;
+
+        topology
+            .clamp_range((b - r) / h, (b + r) / h, n)
+            .filter_map(move |i| {
+                Some((i, topology.wrap_index(i, n)?))
+            })
+            .flat_map(move |(i, wrapped_i)| {
+                // Get the min/max j we have to care about for i
+                let j_range = if i == circle_center_i {
+                    topology.clamp_range((a - r) / w, (a + r) / w, m)
+                } else {
+                    let i_for_line =
+                        if i < circle_center_i { i + 1 } else { i };
+
+                    let delta = r
+                        * (1.0
+                            - ((i_for_line as f32 * h - b) / r)
+                                .powi(2))
+                        .max(0.0)
+                        .sqrt();
+
+                    topology.clamp_range(
+                        (a - delta) / w,
+                        (a + delta) / w,
+                        m,
+                    )
+                };
+
+                j_range.filter_map(move |j| {
+                    let wrapped_j = topology.wrap_index(j, m)?;
+
+                    // Center of the cell
+                    let (center_x, center_y) = (
+                        j as f32 * w + w / 2.0,
+                        i as f32 * h + h / 2.0,
+                    );
+
+                    // true as isize = 1
+                    // false as isize = 0
+                    let (i_delta, j_delta) = (
+                        (center_y > b) as isize, // If the cell is in the 1st or 2nd quadrant
+                        (center_x > a) as isize, // If the cell is in the 1st or 4th quadrant
+                    );
+
+                    Some(QueriedCell {
+                        i:             wrapped_i,
+                        j:             wrapped_j,
+                        fully_covered: (((j + j_delta) as f32) * w
+                            - a)
+                            .powi(2)
+                            + (((i + i_delta) as f32) * h - b)
+                                .powi(2)
+                            < r.powi(2),
+                        offset:        vec2(
+                            (j - wrapped_j as isize) as f32 * w,
+                            (i - wrapped_i as isize) as f32 * h,
+                        ),
+                    })
+                })
+            })
+    }
+
+    /// Get the cells the rectangle covers or touches.
+    fn cells_in_rect(
+        topology: Topology,
+        rect: Rect,
+    ) -> impl Iterator<Item = QueriedCell> {
+        let (w, h) = (CELLS.cell_width, CELLS.cell_height);
+        let (m, n) = (CELLS.columns, CELLS.rows);
+
+        let i_range = topology.clamp_range(
+            rect.y / h,
+            (rect.y + rect.h) / h,
+            n,
+        );
+        let j_range = topology.clamp_range(
+            rect.x / w,
+            (rect.x + rect.w) / w,
+            m,
+        );
+        let (i_start, i_end) = (*i_range.start(), *i_range.end());
+        let (j_start, j_end) = (*j_range.start(), *j_range.end());
+
+        i_range
+            .filter_map(move |i| {
+                Some((i, topology.wrap_index(i, n)?))
+            })
+            .flat_map(move |(i, wrapped_i)| {
+                let i_fully_within_rectangle =
+                    i != i_start && i != i_end;
+
+                j_range.clone().filter_map(move |j| {
+                    let wrapped_j = topology.wrap_index(j, m)?;
+
+                    let j_fully_within_rectangle =
+                        j != j_start && j != j_end;
+
+                    Some(QueriedCell {
+                        i:             wrapped_i,
+                        j:             wrapped_j,
+                        fully_covered: i_fully_within_rectangle
+                            && j_fully_within_rectangle,
+                        offset:        vec2(
+                            (j - wrapped_j as isize) as f32 * w,
+                            (i - wrapped_i as isize) as f32 * h,
+                        ),
+                    })
+                })
+            })
+    }
+
+    #[inline(always)]
+    /// Get the objects within the circle (its border included).
+    pub fn query_circle(
+        &self,
+        circle: Circle,
+    ) -> impl Iterator<Item = (&ObjectId, &T)> {
+        let center = vec2(circle.x, circle.y);
+
+        Self::cells_in_circle(self.topology, circle).flat_map(
+            move |QueriedCell {
+                      i,
+                      j,
+                      fully_covered,
+                      offset,
+                  }| {
+                self.cells[i][j].iter().filter(move |(_, object)| {
+                    fully_covered
+                        || (object.pos() + offset).distance(center)
+                            <= circle.r
+                })
+            },
+        )
+    }
+
+    #[inline(always)]
+    /// Call `f` for every object within the circle (its border
+    /// included).
+    pub fn for_each_in_circle_mut(
+        &mut self,
+        circle: Circle,
+        mut f: impl FnMut(&ObjectId, &mut T),
+    ) {
+        let center = vec2(circle.x, circle.y);
+
+        // The objects may change in any way
+        self.changes = None;
+
+        for QueriedCell {
+            i,
+            j,
+            fully_covered,
+            offset,
+        } in Self::cells_in_circle(self.topology, circle)
+        {
+            for (id, object) in &mut self.cells[i][j] {
+                if fully_covered
+                    || (object.pos() + offset).distance(center)
+                        <= circle.r
+                {
+                    f(id, object);
+                }
+            }
+        }
+    }
+
+    #[inline(always)]
+    /// Remove the objects within the circle (its border included) `f`
+    /// returns `false` for.
+    pub fn retain_in_circle(
+        &mut self,
+        circle: Circle,
+        mut f: impl FnMut(&ObjectId, &mut T) -> bool,
+    ) {
+        let center = vec2(circle.x, circle.y);
+        let mut removed = Vec::new();
+
+        for QueriedCell {
+            i,
+            j,
+            fully_covered,
+            offset,
+        } in Self::cells_in_circle(self.topology, circle)
+        {
+            self.cells[i][j].retain(|id, object| {
+                !(fully_covered
+                    || (object.pos() + offset).distance(center)
+                        <= circle.r)
+                    || f(id, object)
+                    || {
+                        removed.push((*id, object.clone()));
+                        false
+                    }
+            });
+        }
+
+        for (id, object) in &removed {
+            self.remove_dense(id);
+            self.record_change(object, false);
+        }
+    }
+
+    #[inline(always)]
+    /// Get the objects within the rectangle.
+    pub fn query_rect(
+        &self,
+        rect: Rect,
+    ) -> impl Iterator<Item = (&ObjectId, &T)> {
+        Self::cells_in_rect(self.topology, rect).flat_map(
+            move |QueriedCell {
+                      i,
+                      j,
+                      fully_covered,
+                      offset,
+                  }| {
+                self.cells[i][j].iter().filter(move |(_, object)| {
+                    fully_covered
+                        || rect.contains(object.pos() + offset)
+                })
+            },
+        )
+    }
+
+    /// Get up to `k` objects closest to `pos`, the closest first. The
+    /// borders are treated the `topology` way, so what's picked on the
+    /// screen can be looked for without going through them.
+    pub fn k_nearest(
+        &self,
+        pos: Vec2,
+        k: usize,
+        topology: Topology,
+    ) -> Vec<(&ObjectId, &T)> {
+        if k == 0 {
+            return Vec::new();
+        }
+
+        let (m, n) = (CELLS.columns, CELLS.rows);
+        let Cell {
+            i: center_i,
+            j: center_j,
+        } = CELLS.get_cell_by_pos(pos);
+
+        let mut visited = HashSet::new();
+        let mut found: Vec<(f32, (&ObjectId, &T))> = Vec::new();
+
+        // Look through the rings of cells around the cell of `pos`
+        for ring in 0..=m.max(n) as isize {
+            for i in
+                center_i as isize - ring..=center_i as isize + ring
+            {
+                for j in center_j as isize - ring
+                    ..=center_j as isize + ring
+                {
+                    let is_on_ring = (i - center_i as isize).abs()
+                        == ring
+                        || (j - center_j as isize).abs() == ring;
+
+                    if !is_on_ring {
+                        continue;
+                    }
+
+                    let (Some(i), Some(j)) = (
+                        topology.wrap_index(i, n),
+                        topology.wrap_index(j, m),
+                    ) else {
+                        continue;
+                    };
+
+                    if !visited.insert((i, j)) {
+                        continue;
+                    }
+
+                    found.extend(self.cells[i][j].iter().map(
+                        |(id, object)| {
+                            (
+                                topology.distance(pos, object.pos()),
+                                (id, object),
+                            )
+                        },
+                    ));
+                }
+            }
+
+            found.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+            found.truncate(k);
+
+            // Nothing in the next rings can be closer than this
+            let next_ring_distance =
+                ring as f32 * CELLS.cell_width.min(CELLS.cell_height);
+
+            if found.len() == k
+                && found.last().is_some_and(|(distance, _)| {
+                    *distance <= next_ring_distance
+                })
+            {
+                break;
+            }
+        }
+
+        found.into_iter().map(|(_, object)| object).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Plant, PlantKind};
+
+    /// A grid with one plant right at the left border, halfway down.
+    fn grid(topology: Topology) -> SpatialGrid<Plant> {
+        let mut grid = SpatialGrid::new(topology);
+
+        grid.insert(
+            0,
+            Plant {
+                pos:  vec2(1.0, AREA_SIZE.y / 2.0),
+                kind: PlantKind::Grass,
+            },
+        );
+
+        grid
+    }
+
+    #[test]
+    fn queries_go_through_the_borders_when_toroidal() {
+        // Right at the opposite border
+        let pos = vec2(AREA_SIZE.x - 1.0, AREA_SIZE.y / 2.0);
+        let circle = Circle::new(pos.x, pos.y, 10.0);
+
+        let toroidal = grid(Topology::Toroidal);
+        assert_eq!(toroidal.query_circle(circle).count(), 1);
+        assert!(
+            Topology::Toroidal
+                .distance(pos, toroidal.get(&0).unwrap().pos)
+                < 3.0
+        );
+        assert_eq!(
+            toroidal.k_nearest(pos, 1, Topology::Toroidal)[0].0,
+            &0
+        );
+
+        let bounded = grid(Topology::Bounded);
+        assert_eq!(bounded.query_circle(circle).count(), 0);
+        assert!(bounded
+            .k_nearest(pos, 0, Topology::Bounded)
+            .is_empty());
+    }
+
+    #[test]
+    fn changes_are_tracked_once_drained() {
+        let mut grid = grid(Topology::Toroidal);
+
+        for id in 1..4 {
+            grid.insert(
+                id,
+                Plant {
+                    pos:  vec2(id as f32, 1.0),
+                    kind: PlantKind::Grass,
+                },
+            );
+        }
+
+        // Not tracked yet, so everything has to be looked through
+        assert!(grid.drain_changes().is_none());
+        assert_eq!(grid.drain_changes().unwrap().count(), 0);
+
+        let plant = Plant {
+            pos:  vec2(AREA_SIZE.x / 2.0, AREA_SIZE.y / 2.0),
+            kind: PlantKind::Banana,
+        };
+        grid.insert(4, plant.clone());
+        grid.remove(&0, vec2(1.0, AREA_SIZE.y / 2.0));
+
+        let changes =
+            grid.drain_changes().unwrap().collect::<Vec<_>>();
+        assert_eq!(changes.len(), 2);
+        assert!(changes[0] == (plant, true));
+        assert_eq!(changes[1].0.kind, PlantKind::Grass);
+        assert!(!changes[1].1);
+    }
+}
@@ -0,0 +1,341 @@
+use crate::{
+    constants::*, Body, Condition, ConditionRegion, EatingStrategy,
+    Plant, SpatialGrid,
+};
+use macroquad::prelude::{
+    draw_line, draw_rectangle, draw_text, screen_height, Color, Rect,
+    BLUE, GREEN, ORANGE, RED, WHITE,
+};
+use rustc_hash::FxHashMap;
+use std::collections::VecDeque;
+
+/// What the evolution was like at a tick.
+struct Sample {
+    bodies_n:                [usize; EatingStrategy::ALL.len()],
+    body_types_n:            FxHashMap<u32, usize>,
+    plants_n:                usize,
+    average_energy:          f32,
+    average_speed:           f32,
+    average_vision_distance: f32,
+    /// The part of the bodies infected with any virus, within [0, 1].
+    infected_part:           f32,
+    /// Whether there's a region of the condition.
+    conditions:              [bool; Condition::ALL.len()],
+}
+
+/// The history of the evolution drawn as charts, so that the trends can
+/// be seen.
+pub struct Charts {
+    pub show:         bool,
+    /// The newest sample is at the back.
+    samples:          VecDeque<Sample>,
+    /// The colors of the body types of the newest sample, which are the
+    /// only ones drawn.
+    body_type_colors: FxHashMap<u32, Color>,
+}
+
+/// A line of a chart.
+struct Series {
+    label:  String,
+    values: Vec<f32>,
+    color:  Color,
+}
+
+impl Charts {
+    pub fn new() -> Self {
+        Self {
+            show:             false,
+            samples:          VecDeque::with_capacity(
+                CHART_SAMPLES_N,
+            ),
+            body_type_colors: FxHashMap::default(),
+        }
+    }
+
+    /// Remember what the evolution is like now, forgetting the oldest
+    /// sample if there are too many.
+    pub fn record(
+        &mut self,
+        bodies: &SpatialGrid<Body>,
+        plants: &SpatialGrid<Plant>,
+        conditions: &[ConditionRegion],
+    ) {
+        let mut bodies_n = [0; EatingStrategy::ALL.len()];
+        let mut body_types_n = FxHashMap::default();
+        let (mut energy, mut speed, mut vision_distance) =
+            (0.0, 0.0, 0.0);
+        let mut infected_n = 0;
+
+        for body in bodies.values() {
+            bodies_n[body.eating_strategy as usize] += 1;
+            *body_types_n.entry(body.body_type).or_insert(0) += 1;
+            self.body_type_colors.insert(body.body_type, body.color);
+
+            energy += body.energy;
+            speed += body.speed;
+            vision_distance += body.vision_distance;
+
+            if !body.viruses.is_empty() {
+                infected_n += 1;
+            }
+        }
+
+        // The types that have died out don't pile up
+        self.body_type_colors.retain(|body_type, _| {
+            body_types_n.contains_key(body_type)
+        });
+
+        let n = bodies.len().max(1) as f32;
+
+        if self.samples.len() == CHART_SAMPLES_N {
+            self.samples.pop_front();
+        }
+
+        self.samples.push_back(Sample {
+            bodies_n,
+            body_types_n,
+            plants_n: plants.len(),
+            average_energy: energy / n,
+            average_speed: speed / n,
+            average_vision_distance: vision_distance / n,
+            infected_part: infected_n as f32 / n,
+            conditions: Condition::ALL.map(|condition| {
+                conditions
+                    .iter()
+                    .any(|region| region.condition == condition)
+            }),
+        });
+    }
+
+    #[inline(always)]
+    fn get_values(&self, f: impl Fn(&Sample) -> f32) -> Vec<f32> {
+        self.samples.iter().map(f).collect()
+    }
+
+    /// Has to be drawn with the default camera.
+    pub fn draw(&self) {
+        if !self.show || self.samples.is_empty() {
+            return;
+        }
+
+        let last = self.samples.back().unwrap();
+
+        // The body types that are the most numerous now
+        let mut top_body_types =
+            last.body_types_n.iter().collect::<Vec<_>>();
+        top_body_types.sort_unstable_by(
+            |(a_type, a_n), (b_type, b_n)| {
+                b_n.cmp(a_n).then(a_type.cmp(b_type))
+            },
+        );
+        top_body_types.truncate(CHART_TOP_BODY_TYPES_N);
+
+        let charts = [
+            (
+                "bodies",
+                EatingStrategy::ALL
+                    .iter()
+                    .map(|eating_strategy| Series {
+                        label:  format!("{:?}", eating_strategy),
+                        values: self.get_values(|sample| {
+                            sample.bodies_n[*eating_strategy as usize]
+                                as f32
+                        }),
+                        color:  eating_strategy.get_color(),
+                    })
+                    .collect::<Vec<_>>(),
+                false,
+            ),
+            (
+                "top body types",
+                top_body_types
+                    .iter()
+                    .map(|(body_type, _)| Series {
+                        label:  format!("type {}", body_type),
+                        values: self.get_values(|sample| {
+                            sample
+                                .body_types_n
+                                .get(body_type)
+                                .copied()
+                                .unwrap_or(0)
+                                as f32
+                        }),
+                        color:  self.body_type_colors[body_type],
+                    })
+                    .collect(),
+                false,
+            ),
+            (
+                "plants",
+                vec![Series {
+                    label:  "plants".to_owned(),
+                    values: self
+                        .get_values(|sample| sample.plants_n as f32),
+                    color:  GREEN,
+                }],
+                false,
+            ),
+            (
+                "average traits (relative)",
+                vec![
+                    Series {
+                        label:  "energy".to_owned(),
+                        values: self.get_values(|sample| {
+                            sample.average_energy
+                        }),
+                        color:  ORANGE,
+                    },
+                    Series {
+                        label:  "speed".to_owned(),
+                        values: self.get_values(|sample| {
+                            sample.average_speed
+                        }),
+                        color:  RED,
+                    },
+                    Series {
+                        label:  "vision".to_owned(),
+                        values: self.get_values(|sample| {
+                            sample.average_vision_distance
+                        }),
+                        color:  BLUE,
+                    },
+                ],
+                true,
+            ),
+            (
+                "viruses",
+                vec![Series {
+                    label:  "infected (%)".to_owned(),
+                    values: self.get_values(|sample| {
+                        sample.infected_part * 100.0
+                    }),
+                    color:  RED,
+                }],
+                false,
+            ),
+        ];
+
+        let chart_full_height = CHART_HEIGHT
+            + PANEL_FONT_SIZE as f32
+            + PANEL_LINE_GAP
+            + PANEL_PADDING;
+        let mut y = screen_height()
+            - CHARTS_BOTTOM
+            - chart_full_height * charts.len() as f32;
+
+        draw_rectangle(
+            0.0,
+            y - PANEL_PADDING,
+            CHART_WIDTH + PANEL_PADDING * 2.0,
+            chart_full_height * charts.len() as f32 + PANEL_PADDING,
+            Color::new(0.0, 0.0, 0.0, PANEL_ALPHA),
+        );
+
+        for (title, series, normalize) in charts {
+            y += PANEL_FONT_SIZE as f32;
+
+            // The title goes with the current values
+            let values = series
+                .iter()
+                .map(|series| {
+                    let value = series.values.last().unwrap();
+
+                    if normalize {
+                        format!("{} {:.1}", series.label, value)
+                    } else {
+                        format!("{} {:.0}", series.label, value)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            let text = format!("{title}: {values}");
+
+            draw_text(
+                &text,
+                PANEL_PADDING,
+                y,
+                PANEL_FONT_SIZE as f32,
+                WHITE,
+            );
+
+            y += PANEL_LINE_GAP;
+
+            self.draw_chart(
+                Rect::new(
+                    PANEL_PADDING,
+                    y,
+                    CHART_WIDTH,
+                    CHART_HEIGHT,
+                ),
+                &series,
+                normalize,
+            );
+
+            y += CHART_HEIGHT + PANEL_PADDING;
+        }
+    }
+
+    /// Draw the series from 0 to the highest value, or each one from 0
+    /// to its own highest value if they're normalized.
+    fn draw_chart(
+        &self,
+        rect: Rect,
+        series: &[Series],
+        normalize: bool,
+    ) {
+        let step = rect.w / (CHART_SAMPLES_N - 1) as f32;
+
+        // The conditions go behind the lines
+        for (i, sample) in self.samples.iter().enumerate() {
+            for (condition, is_active) in
+                Condition::ALL.iter().zip(sample.conditions)
+            {
+                if is_active {
+                    draw_rectangle(
+                        rect.x + step * i as f32,
+                        rect.y,
+                        step,
+                        rect.h,
+                        condition.get_color(),
+                    );
+                }
+            }
+        }
+
+        let get_max = |values: &[f32]| {
+            values.iter().copied().fold(f32::EPSILON, f32::max)
+        };
+        let common_max = series
+            .iter()
+            .map(|series| get_max(&series.values))
+            .fold(f32::EPSILON, f32::max);
+
+        for series in series {
+            let max = if normalize {
+                get_max(&series.values)
+            } else {
+                common_max
+            };
+
+            for (i, pair) in series.values.windows(2).enumerate() {
+                draw_line(
+                    rect.x + step * i as f32,
+                    rect.y + rect.h * (1.0 - pair[0] / max),
+                    rect.x + step * (i + 1) as f32,
+                    rect.y + rect.h * (1.0 - pair[1] / max),
+                    CHART_LINE_THICKNESS,
+                    series.color,
+                );
+            }
+        }
+
+        draw_line(
+            rect.x,
+            rect.y + rect.h,
+            rect.x + rect.w,
+            rect.y + rect.h,
+            1.0,
+            WHITE,
+        );
+    }
+}
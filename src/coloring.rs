@@ -0,0 +1,182 @@
+use crate::{
+    constants::*, draw_legend, get_gradient_color, Body, Skill,
+    SpatialGrid,
+};
+use macroquad::prelude::Color;
+
+#[derive(Clone, Copy, PartialEq)]
+/// What the bodies are colored by.
+pub enum ColorMode {
+    BodyType,
+    /// Relative to the division threshold.
+    Energy,
+    Speed,
+    VisionDistance,
+    SkillsN,
+    /// Whether the body has the chosen skill.
+    HasSkill,
+    /// The part of the lifespan that's left.
+    LifespanLeft,
+    Generation,
+}
+
+impl ColorMode {
+    pub const ALL: [Self; 8] = [
+        Self::BodyType,
+        Self::Energy,
+        Self::Speed,
+        Self::VisionDistance,
+        Self::SkillsN,
+        Self::HasSkill,
+        Self::LifespanLeft,
+        Self::Generation,
+    ];
+}
+
+/// How the bodies are colored, with the range of the values the
+/// gradient goes through.
+pub struct Coloring {
+    /// The index of the mode within `ColorMode::ALL`.
+    mode_i:  usize,
+    /// The index of the skill within `Skill::ALL` for `HasSkill`.
+    skill_i: usize,
+    range:   (f32, f32),
+}
+
+impl Coloring {
+    pub fn new() -> Self {
+        Self {
+            mode_i:  0,
+            skill_i: 0,
+            range:   (0.0, 0.0),
+        }
+    }
+
+    #[inline(always)]
+    pub fn next_mode(&mut self) {
+        self.mode_i = (self.mode_i + 1) % ColorMode::ALL.len();
+    }
+
+    #[inline(always)]
+    /// Choose the next skill and switch to `HasSkill`.
+    pub fn next_skill(&mut self) {
+        let has_skill_i = ColorMode::ALL
+            .iter()
+            .position(|mode| mode == &ColorMode::HasSkill)
+            .unwrap();
+
+        if self.mode_i == has_skill_i {
+            self.skill_i = (self.skill_i + 1) % Skill::ALL.len();
+        } else {
+            self.mode_i = has_skill_i;
+        }
+    }
+
+    #[inline(always)]
+    fn get_mode(&self) -> ColorMode {
+        ColorMode::ALL[self.mode_i]
+    }
+
+    #[inline(always)]
+    fn get_skill(&self) -> Skill {
+        Skill::ALL[self.skill_i]
+    }
+
+    #[inline(always)]
+    fn get_value(&self, body: &Body) -> f32 {
+        match self.get_mode() {
+            ColorMode::BodyType => 0.0,
+            ColorMode::Energy => {
+                body.energy / body.division_threshold
+            }
+            ColorMode::Speed => body.speed,
+            ColorMode::VisionDistance => body.vision_distance,
+            ColorMode::SkillsN => body.skills.len() as f32,
+            ColorMode::HasSkill => {
+                if body.skills.contains(self.get_skill()) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            ColorMode::LifespanLeft => {
+                1.0 - body.age as f32
+                    / SECOND_TICKS_N as f32
+                    / body.lifespan
+            }
+            ColorMode::Generation => body.generation as f32,
+        }
+    }
+
+    /// Find the range of the values of the bodies for the modes that
+    /// don't have a fixed one.
+    pub fn update_range(&mut self, bodies: &SpatialGrid<Body>) {
+        self.range = match self.get_mode() {
+            ColorMode::BodyType => (0.0, 0.0),
+            ColorMode::Energy
+            | ColorMode::HasSkill
+            | ColorMode::LifespanLeft => (0.0, 1.0),
+            ColorMode::SkillsN => (0.0, Skill::ALL.len() as f32),
+            ColorMode::Speed
+            | ColorMode::VisionDistance
+            | ColorMode::Generation => {
+                let (min, max) = bodies
+                    .values()
+                    .map(|body| self.get_value(body))
+                    .fold(
+                        (f32::MAX, f32::MIN),
+                        |(min, max), value| {
+                            (min.min(value), max.max(value))
+                        },
+                    );
+
+                if min > max {
+                    // Nothing to show
+                    (0.0, 0.0)
+                } else {
+                    (min, max)
+                }
+            }
+        };
+    }
+
+    #[inline(always)]
+    pub fn get_color(&self, body: &Body) -> Color {
+        if let ColorMode::BodyType = self.get_mode() {
+            return body.color;
+        }
+
+        let (min, max) = self.range;
+
+        get_gradient_color(if max > min {
+            (self.get_value(body) - min) / (max - min)
+        } else {
+            1.0
+        })
+    }
+
+    /// Has to be drawn with the default camera.
+    pub fn draw_legend(&self) {
+        let title = match self.get_mode() {
+            ColorMode::BodyType => return,
+            ColorMode::Energy => {
+                "energy / division threshold".to_owned()
+            }
+            ColorMode::Speed => "speed".to_owned(),
+            ColorMode::VisionDistance => "vision distance".to_owned(),
+            ColorMode::SkillsN => "number of skills".to_owned(),
+            ColorMode::HasSkill => {
+                format!("has {:?}", self.get_skill())
+            }
+            ColorMode::LifespanLeft => "lifespan left".to_owned(),
+            ColorMode::Generation => "generation".to_owned(),
+        };
+
+        draw_legend(
+            &title,
+            self.range.0,
+            self.range.1,
+            COLORING_LEGEND_ROW,
+        );
+    }
+}
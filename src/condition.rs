@@ -1,8 +1,12 @@
-use crate::USER_CONSTANTS;
+use crate::{constants::*, UserConstants, Zoom, AREA_SIZE};
+use macroquad::prelude::{
+    draw_circle, vec2, Circle, Color, Vec2, BLUE, ORANGE,
+};
 use rand::{prelude::IteratorRandom, rngs::StdRng, Rng};
-use std::time::{Duration, Instant};
+use serde_derive::Deserialize;
+use std::f32::consts::PI;
 
-#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug, Deserialize)]
 pub enum Condition {
     Drought,
     Rain,
@@ -12,39 +16,150 @@ impl Condition {
     pub const ALL: [Self; 2] = [Self::Drought, Self::Rain];
 
     #[inline(always)]
-    pub fn update_condition(
-        condition: &mut Option<(Condition, (Instant, Duration))>,
+    /// The color of the overlay drawn over the region of the condition.
+    pub fn get_color(&self) -> Color {
+        let color = match self {
+            Condition::Drought => ORANGE,
+            Condition::Rain => BLUE,
+        };
+
+        Color::new(color.r, color.g, color.b, CONDITION_ALPHA)
+    }
+
+    #[inline(always)]
+    /// Remove the regions that have expired, make the rest drift and
+    /// possibly spawn a new one if there are fewer than the maximum.
+    pub fn update_conditions(
+        regions: &mut Vec<ConditionRegion>,
+        user_constants: &UserConstants,
         rng: &mut StdRng,
     ) {
-        match condition {
-            Some((_, (timestamp, lifetime))) => {
-                if &timestamp.elapsed() > lifetime {
-                    *condition = None;
-                }
-            }
-            None => {
-                let user_constants = USER_CONSTANTS.read().unwrap();
-
-                if user_constants.condition_chance > 0.0
-                    && (user_constants.condition_chance as usize == 1
-                        || rng.gen_range(0.0..1.0)
-                            <= user_constants.condition_chance)
-                {
-                    *condition = Some((
-                        *Self::ALL.iter().choose(rng).unwrap(),
-                        (
-                            Instant::now(),
-                            Duration::from_secs(
-                                rng.gen_range(
-                                    user_constants
-                                        .condition_lifetime
-                                        .clone(),
-                                ),
-                            ),
-                        ),
-                    ));
-                }
-            }
+        regions.retain(|region| region.age <= region.lifetime);
+
+        for region in regions.iter_mut() {
+            region.age += 1;
+            region.drift();
+        }
+
+        if regions.len() < user_constants.max_condition_regions
+            && user_constants.condition_chance > 0.0
+            && (user_constants.condition_chance as usize == 1
+                || rng.gen_range(0.0..1.0)
+                    <= user_constants.condition_chance)
+        {
+            let condition = *Self::ALL.iter().choose(rng).unwrap();
+            let circle = Circle::new(
+                rng.gen_range(0.0..AREA_SIZE.x),
+                rng.gen_range(0.0..AREA_SIZE.y),
+                rng.gen_range(
+                    user_constants.condition_radius.clone(),
+                ),
+            );
+            let lifetime = rng
+                .gen_range(user_constants.condition_lifetime.clone())
+                * SECOND_TICKS_N;
+
+            regions.push(ConditionRegion::new(
+                condition,
+                circle,
+                lifetime,
+                user_constants,
+                rng,
+            ));
+        }
+    }
+
+    #[inline(always)]
+    /// Whether `pos` is within a region of the condition.
+    pub fn is_at(
+        &self,
+        regions: &[ConditionRegion],
+        pos: Vec2,
+    ) -> bool {
+        regions.iter().any(|region| {
+            &region.condition == self && region.contains(pos)
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+/// A condition that only affects what's inside its circle and drifts
+/// across the area.
+pub struct ConditionRegion {
+    pub condition: Condition,
+    pub circle:    Circle,
+    /// How far the region moves in one tick.
+    pub drift:     Vec2,
+    /// In ticks.
+    pub age:       u64,
+    /// In ticks.
+    pub lifetime:  u64,
+}
+
+impl ConditionRegion {
+    /// Create a region drifting in a random direction.
+    pub fn new(
+        condition: Condition,
+        circle: Circle,
+        lifetime: u64,
+        user_constants: &UserConstants,
+        rng: &mut StdRng,
+    ) -> Self {
+        let drift_speed = user_constants.condition_drift_speed;
+        let drift_angle = rng.gen_range(0.0..2.0 * PI);
+
+        Self {
+            condition,
+            circle,
+            drift: vec2(
+                drift_speed * drift_angle.cos(),
+                drift_speed * drift_angle.sin(),
+            ),
+            age: 0,
+            lifetime,
+        }
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, pos: Vec2) -> bool {
+        self.circle.contains(&pos)
+    }
+
+    #[inline(always)]
+    pub fn area(&self) -> f32 {
+        PI * self.circle.r.powi(2)
+    }
+
+    #[inline(always)]
+    /// Move the region and bounce it off the border its center has
+    /// reached, so that it never jumps.
+    pub fn drift(&mut self) {
+        self.circle.x += self.drift.x;
+        self.circle.y += self.drift.y;
+
+        if !(0.0..=AREA_SIZE.x).contains(&self.circle.x) {
+            self.circle.x = self.circle.x.clamp(0.0, AREA_SIZE.x);
+            self.drift.x = -self.drift.x;
+        }
+
+        if !(0.0..=AREA_SIZE.y).contains(&self.circle.y) {
+            self.circle.y = self.circle.y.clamp(0.0, AREA_SIZE.y);
+            self.drift.y = -self.drift.y;
         }
     }
+
+    pub fn draw(&self, zoom: &Zoom) {
+        if zoom.zoomed
+            && !self.circle.overlaps_rect(&zoom.rect.unwrap())
+        {
+            return;
+        }
+
+        draw_circle(
+            self.circle.x,
+            self.circle.y,
+            self.circle.r,
+            self.condition.get_color(),
+        );
+    }
 }
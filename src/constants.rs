@@ -1,3 +1,4 @@
+use macroquad::color::{Color, BLUE, GREEN, RED, YELLOW};
 use std::{
     f32::consts::SQRT_3,
     sync::{LazyLock, RwLock},
@@ -8,6 +9,11 @@ pub const DEFAULT_SCREEN_HEIGHT: f32 = 1080.0;
 
 // TOML
 pub const CONFIG_FILE_NAME: &str = "config.toml";
+pub const INTERVENTIONS_FILE_NAME: &str = "interventions.toml";
+/// Can be used as `INTERVENTIONS_FILE_NAME` to replay the run.
+pub const INTERVENTIONS_LOG_FILE_NAME: &str =
+    "interventions_log.toml";
+pub const SCENARIO_FILE_NAME: &str = "scenario.toml";
 
 // Base
 pub const DEFAULT_CELL_ROWS: usize = 129; // May be needed to be changed when the perfect values for the
@@ -23,17 +29,19 @@ pub static DEFAULT_AREA_SIZE_RATIO: f32 =
     DEFAULT_SCREEN_WIDTH / DEFAULT_SCREEN_HEIGHT;
 
 pub const OBJECT_RADIUS: f32 = 10.0;
-/// Used for `get_with_deviation`.
+/// Used for `Palette`.
 pub const COLOR_MIN: u8 = 80;
 pub const COLOR_MAX: u8 = 255;
+/// How many random colors are compared to allocate one.
+pub const PALETTE_CANDIDATES_N: usize = 100;
 
 // Evolution process
 pub const GRASS_ENERGY: f32 = 100.0;
 pub const BANANA_ENERGY: f32 = GRASS_ENERGY * 2.0;
 
 pub const MIN_GAP: f32 = 3.0;
-pub const COLOR_GAP: f32 = 0.6; // Depends on COLOR_MIN and COLOR_MAX
-pub const PLANT_SPAWN_TIME_LIMIT: u64 = 5; // In millis
+/// How many random positions are tried to spawn a plant.
+pub const PLANT_SPAWN_ATTEMPTS_N: usize = 30;
 
 // Plants
 pub static PLANTS_N_FOR_ONE_STEP: LazyLock<RwLock<usize>> =
@@ -42,19 +50,145 @@ pub static PLANTS_N_FOR_ONE_STEP: LazyLock<RwLock<usize>> =
 // Conditions
 pub const RAIN_PLANTS_N_FOR_ONE_STEP_MULTIPLIER: f32 = 2.7;
 pub const DROUGHT_PLANT_DIE_CHANCE_MULTIPLIER: f32 = 1.2;
+pub const DROUGHT_ENERGY_SPENT_MULTIPLIER: f32 = 1.3;
+pub const CONDITION_ALPHA: f32 = 0.15;
+
+// Interventions
+pub const INTERVENTION_RADIUS: f32 = 1000.0;
+pub const PLANT_BLOOM_N: usize = 300;
+
+// Editor
+pub const EDITOR_BRUSH_RADIUS: f32 = 500.0;
+pub const EDITOR_BRUSH_MIN_RADIUS: f32 = 100.0;
+pub const EDITOR_BRUSH_MAX_RADIUS: f32 = 5000.0;
+/// How many times one step changes the radius of the brush by.
+pub const EDITOR_BRUSH_STEP: f32 = 1.25;
+pub const EDITOR_BRUSH_THICKNESS: f32 = 10.0;
+pub const EDITOR_BRUSH_ALPHA: f32 = 0.5;
+/// How many plants a patch has per unit of the area.
+pub const EDITOR_PLANTS_DENSITY: f32 = 0.0001;
 
 // UI
 pub const EVOLUTION_INFO_FONT_SIZE: u16 = 500;
 pub const FPS_FONT_SIZE: u16 = 800;
+pub const SPEED_FONT_SIZE: u16 = 500;
 
 pub const EVOLUTION_INFO_GAP: f32 = 100.0;
 
+// Panels (in pixels)
+pub const PANEL_FONT_SIZE: u16 = 20;
+pub const PANEL_LINE_GAP: f32 = 4.0;
+pub const PANEL_PADDING: f32 = 10.0;
+pub const PANEL_ALPHA: f32 = 0.6;
+/// Leaves room for the FPS.
+pub const PANEL_TOP: f32 = 100.0;
+/// Leaves room for the info about the evolution.
+pub const FILTER_PANEL_TOP: f32 = 250.0;
+/// How visible the bodies filtered out are when they're dimmed.
+pub const FILTER_DIM_ALPHA: f32 = 0.15;
+/// The gap between the selected body and the circle around it.
+pub const SELECTION_RING_GAP: f32 = 5.0;
+
+// Minimap (in pixels)
+pub const MINIMAP_WIDTH: f32 = 300.0;
+pub const MINIMAP_MARGIN: f32 = 10.0;
+pub const MINIMAP_TEXEL_SIZE: f32 = 4.0;
+/// How many plants make a texel of the minimap opaque.
+pub const MINIMAP_SATURATION_N: f32 = 4.0;
+/// The plants are dimmer than the bodies.
+pub const MINIMAP_PLANT_ALPHA: f32 = 0.5;
+
+// Charts
+/// How many samples of the history are kept.
+pub const CHART_SAMPLES_N: usize = 300;
+/// How often the samples are taken.
+pub const CHART_SAMPLE_TICKS_N: u64 = SECOND_TICKS_N / 2;
+pub const CHART_TOP_BODY_TYPES_N: usize = 3;
+/// In pixels.
+pub const CHART_WIDTH: f32 = 600.0;
+pub const CHART_HEIGHT: f32 = 70.0;
+pub const CHART_LINE_THICKNESS: f32 = 1.5;
+/// Leaves room for the speed.
+pub const CHARTS_BOTTOM: f32 = 60.0;
+
+// Histograms (in pixels)
+pub const HISTOGRAM_WIDTH: f32 = 220.0;
+pub const HISTOGRAM_HEIGHT: f32 = 70.0;
+pub const HISTOGRAM_COLUMNS_N: usize = 3;
+pub const HISTOGRAM_BINS_N: usize = 20;
+pub const SKILL_CHART_WIDTH: f32 = 380.0;
+pub const SKILL_CHART_BAR_COLOR: Color =
+    Color::new(0.3, 0.3, 0.6, 1.0);
+
+// Settings
+/// The part of the value one step changes it by.
+pub const SETTINGS_STEP: f64 = 0.1;
+/// The step for the values that are 0.
+pub const SETTINGS_ZERO_STEP: f64 = 0.001;
+/// How many settings fit on the panel.
+pub const SETTINGS_SHOWN_N: usize = 20;
+
+// Legends (in pixels)
+pub const LEGEND_WIDTH: f32 = 300.0;
+pub const LEGEND_BAR_HEIGHT: f32 = 15.0;
+/// How many rectangles the gradient of a legend is made of.
+pub const LEGEND_STEPS_N: usize = 60;
+/// The legends are stacked from the bottom of the screen.
+pub const HEATMAP_LEGEND_ROW: usize = 0;
+pub const COLORING_LEGEND_ROW: usize = 1;
+
+// Heatmap
+/// The colors the values go through from the lowest to the highest.
+pub const GRADIENT: [Color; 4] = [BLUE, GREEN, YELLOW, RED];
+pub const HEATMAP_ALPHA: f32 = 0.6;
+/// How long the deaths are shown for.
+pub const HEATMAP_DEATHS_TICKS_N: u64 = SECOND_TICKS_N * 10;
+
+// Drawing
+/// macroquad can't draw more than 5000 indices with one draw call.
+pub const BATCH_MAX_INDICES_N: usize = 4998;
+pub const CIRCLE_SIDES_N: usize = 20;
+/// The size of the part of the area one texel of the plant density map
+/// covers.
+pub const DENSITY_MAP_TEXEL_SIZE: f32 = OBJECT_RADIUS * 2.0;
+/// How many plants make a texel of the density map opaque.
+pub const DENSITY_MAP_SATURATION_N: f32 = 1.0;
+
 // Zoom
-pub const MAX_ZOOM: f32 = OBJECT_RADIUS;
 pub const MIN_ZOOM: f32 = 1.0;
+/// How many times the zoom changes with one step of the mouse wheel.
+pub const ZOOM_STEP: f32 = 1.2;
+/// How many pixels the view moves for one frame the arrow key is held.
+pub const PAN_SPEED: f32 = 10.0;
+/// How many pixels the cursor has to move for a click to become a drag.
+pub const DRAG_THRESHOLD: f32 = 5.0;
+/// The plants are drawn as a density map until the zoom reaches it.
+pub const DENSITY_MAP_MAX_ZOOM: f32 = 2.0;
 
 // Math
 pub static COSINE_OF_30_DEGREES: f32 = SQRT_3 / 2.0;
 
-// Misc
-pub const FPS: u64 = 144;
+// Scheduler
+/// How many ticks make one second of the evolution. The durations in the
+/// config are counted in ticks, so they don't depend on the speed.
+pub const SECOND_TICKS_N: u64 = 60;
+/// How many ticks the evolution can fall behind the rate before the
+/// missed ones are skipped.
+pub const MAX_TICKS_BEHIND_N: u32 = 10;
+/// How many times faster than `ticks_per_second` the evolution can go.
+pub const SPEEDS: [f32; 9] =
+    [0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0];
+pub const DEFAULT_SPEED_I: usize = 2;
+/// How many ticks can be stepped through with one key press.
+pub const STEP_TICKS_NS: [u64; 4] = [1, 10, 100, 1000];
+pub const DEFAULT_STEP_TICKS_I: usize = 2;
+
+// Config
+// What the keys the older configs don't have are set to
+pub const DEFAULT_MAX_ZOOM: f32 = 10.0;
+pub const DEFAULT_CONDITION_RADIUS: [f32; 2] = [1500.0, 3000.0];
+pub const DEFAULT_CONDITION_DRIFT_SPEED: f32 = 0.5;
+pub const DEFAULT_MAX_CONDITION_REGIONS: usize = 5;
+pub const DEFAULT_THREADS: usize = 0;
+pub const DEFAULT_TICKS_PER_SECOND: u32 = 60;
+pub const DEFAULT_MAX_FPS: u32 = 144;
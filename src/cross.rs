@@ -1,15 +1,18 @@
-use crate::{constants::*, Body, Virus, Zoom};
-use macroquad::prelude::{draw_circle, draw_line, Color, Vec2, RED};
-use std::{collections::HashMap, f32::consts::SQRT_2, time::Instant};
+use crate::{
+    constants::*, Batch, Body, ObjectId, Positioned, Viruses, Zoom,
+};
+use macroquad::prelude::{vec2, Color, Vec2, RED};
+use std::f32::consts::SQRT_2;
 
-pub type CrossId = Instant;
+pub type CrossId = ObjectId;
 
 #[derive(Clone, PartialEq)]
 pub struct Cross {
     pub pos:       Vec2,
-    pub timestamp: Instant,
+    /// How many ticks the cross has been there for.
+    pub age:       u64,
     pub energy:    f32,
-    pub viruses:   HashMap<Virus, f32>,
+    pub viruses:   Viruses,
     pub color:     Color,
     pub body_type: u32,
 }
@@ -18,15 +21,15 @@ impl Cross {
     pub fn new(body: &Body) -> Self {
         Self {
             pos:       body.pos,
-            timestamp: Instant::now(),
+            age:       0,
             energy:    body.energy,
-            viruses:   body.viruses.clone(),
+            viruses:   body.viruses,
             color:     body.color,
             body_type: body.body_type,
         }
     }
 
-    pub fn draw(&self, zoom: &Zoom) {
+    pub fn draw(&self, zoom: &Zoom, batch: &mut Batch) {
         if zoom.zoomed
             && !zoom.extended_rect.unwrap().contains(self.pos)
         {
@@ -43,26 +46,29 @@ impl Cross {
 
         let side_length_half = OBJECT_RADIUS / SQRT_2;
 
-        draw_line(
-            self.pos.x - side_length_half,
-            self.pos.y - side_length_half,
-            self.pos.x + side_length_half,
-            self.pos.y + side_length_half,
+        batch.line(
+            self.pos - side_length_half,
+            self.pos + side_length_half,
             2.0,
             self.color,
         );
 
-        draw_line(
-            self.pos.x + side_length_half,
-            self.pos.y - side_length_half,
-            self.pos.x - side_length_half,
-            self.pos.y + side_length_half,
+        batch.line(
+            self.pos + vec2(side_length_half, -side_length_half),
+            self.pos + vec2(-side_length_half, side_length_half),
             2.0,
             self.color,
         );
 
         if !self.viruses.is_empty() {
-            draw_circle(self.pos.x, self.pos.y, 5.0, RED)
+            batch.circle(self.pos, 5.0, RED)
         }
     }
 }
+
+impl Positioned for Cross {
+    #[inline(always)]
+    fn pos(&self) -> Vec2 {
+        self.pos
+    }
+}
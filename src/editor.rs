@@ -0,0 +1,404 @@
+use crate::{
+    constants::*, draw_text_panel, get_text_panel_size, Batch, Body,
+    Cross, EatingStrategy, Intervention, Palette, PlantKind,
+    Selection, Skill, SkillSet, SpatialGrid, UserConstants, Virus,
+};
+use macroquad::prelude::{
+    is_key_pressed, screen_width, Color, KeyCode, Vec2, WHITE,
+};
+use std::f32::consts::PI;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+/// What a click does while editing.
+pub enum Tool {
+    Plants,
+    Body,
+    /// Place a copy of the selected body.
+    Clone,
+    /// Remove everything within the brush.
+    Erase,
+    Cross,
+}
+
+impl Tool {
+    pub const ALL: [Self; 5] = [
+        Self::Plants,
+        Self::Body,
+        Self::Clone,
+        Self::Erase,
+        Self::Cross,
+    ];
+}
+
+/// Placing and removing things with the mouse. Every edit is an
+/// intervention, so it's logged and can be replayed.
+pub struct Editor {
+    pub active:        bool,
+    /// The index of the tool within `Tool::ALL`.
+    tool_i:            usize,
+    /// The index of the kind within `PlantKind::ALL`.
+    plant_kind_i:      usize,
+    /// The index of the strategy within `EatingStrategy::ALL`.
+    eating_strategy_i: usize,
+    /// `None` if every placed body or cross is of a new type.
+    body_type:         Option<u32>,
+    skills:            SkillSet,
+    /// The index of the skill within `Skill::ALL` that's toggled.
+    skill_i:           usize,
+    /// The bit of a virus within `Virus::ALL` is set if the placed
+    /// bodies and crosses are infected with it.
+    viruses:           usize,
+    brush_radius:      f32,
+    /// The lowest type that hasn't been given out yet, so that the new
+    /// types stay different while the evolution is paused.
+    next_body_type:    u32,
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        Self {
+            active:            false,
+            tool_i:            0,
+            plant_kind_i:      0,
+            eating_strategy_i: 0,
+            body_type:         None,
+            skills:            SkillSet::default(),
+            skill_i:           0,
+            viruses:           0,
+            brush_radius:      EDITOR_BRUSH_RADIUS,
+            next_body_type:    0,
+        }
+    }
+
+    #[inline(always)]
+    fn get_tool(&self) -> Tool {
+        Tool::ALL[self.tool_i]
+    }
+
+    #[inline(always)]
+    fn get_viruses(&self) -> Vec<Virus> {
+        Virus::ALL
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| self.viruses & (1 << i) != 0)
+            .map(|(_, virus)| virus)
+            .collect()
+    }
+
+    /// Turn the editor on or off with P, and change the tool and what
+    /// it places with the keys shown on the panel while it's on.
+    pub fn handle_input(&mut self, bodies: &SpatialGrid<Body>) {
+        if is_key_pressed(KeyCode::P) {
+            self.active = !self.active;
+        }
+
+        if !self.active {
+            return;
+        }
+
+        if is_key_pressed(KeyCode::Q) {
+            self.tool_i = (self.tool_i + 1) % Tool::ALL.len();
+        }
+
+        if is_key_pressed(KeyCode::A) {
+            match self.get_tool() {
+                Tool::Plants => {
+                    self.plant_kind_i = (self.plant_kind_i + 1)
+                        % PlantKind::ALL.len();
+                }
+                Tool::Body => {
+                    self.eating_strategy_i = (self.eating_strategy_i
+                        + 1)
+                        % EatingStrategy::ALL.len();
+                }
+                _ => (),
+            }
+        }
+
+        if is_key_pressed(KeyCode::Y) {
+            let mut body_types = bodies
+                .values()
+                .map(|body| body.body_type)
+                .collect::<Vec<_>>();
+            body_types.sort_unstable();
+            body_types.dedup();
+
+            self.body_type = match self.body_type {
+                None => body_types.first().copied(),
+                Some(body_type) => body_types
+                    .iter()
+                    .find(|other| **other > body_type)
+                    .copied(),
+            };
+        }
+
+        if is_key_pressed(KeyCode::Z) {
+            self.brush_radius = (self.brush_radius
+                / EDITOR_BRUSH_STEP)
+                .max(EDITOR_BRUSH_MIN_RADIUS);
+        }
+
+        if is_key_pressed(KeyCode::X) {
+            self.brush_radius = (self.brush_radius
+                * EDITOR_BRUSH_STEP)
+                .min(EDITOR_BRUSH_MAX_RADIUS);
+        }
+
+        if is_key_pressed(KeyCode::Key4) {
+            self.skill_i = (self.skill_i + 1) % Skill::ALL.len();
+        }
+
+        if is_key_pressed(KeyCode::Key5) {
+            let skill = Skill::ALL[self.skill_i];
+
+            if self.skills.contains(skill) {
+                self.skills.remove(skill);
+            } else {
+                self.skills.insert(skill);
+            }
+        }
+
+        if is_key_pressed(KeyCode::Key6) {
+            self.viruses =
+                (self.viruses + 1) % (1 << Virus::ALL.len());
+        }
+    }
+
+    /// The type and the color of what's placed: the chosen type with the
+    /// color of its bodies or crosses, or a new type with a new color.
+    fn get_body_type(
+        &mut self,
+        bodies: &SpatialGrid<Body>,
+        crosses: &SpatialGrid<Cross>,
+        palette: &mut Palette,
+        user_constants: &UserConstants,
+    ) -> (u32, [u8; 3]) {
+        let (body_type, color) =
+            match self.body_type.and_then(|body_type| {
+                bodies
+                    .values()
+                    .find(|body| body.body_type == body_type)
+                    .map(|body| body.color)
+                    .or_else(|| {
+                        crosses
+                            .values()
+                            .find(|cross| {
+                                cross.body_type == body_type
+                            })
+                            .map(|cross| cross.color)
+                    })
+                    .map(|color| (body_type, color))
+            }) {
+                Some(chosen) => chosen,
+                None => {
+                    let body_type = bodies
+                        .values()
+                        .map(|body| body.body_type)
+                        .chain(
+                            crosses
+                                .values()
+                                .map(|cross| cross.body_type),
+                        )
+                        .max()
+                        .unwrap_or(0)
+                        .max(user_constants.species.len() as u32)
+                        .max(self.next_body_type.saturating_sub(1))
+                        + 1;
+
+                    self.next_body_type = body_type + 1;
+
+                    (body_type, palette.next_color())
+                }
+            };
+
+        let [r, g, b, _]: [u8; 4] = color.into();
+
+        (body_type, [r, g, b])
+    }
+
+    /// The intervention for a click at `pos` in the area, `None` if the
+    /// tool has nothing to do.
+    pub fn get_intervention(
+        &mut self,
+        pos: Vec2,
+        bodies: &SpatialGrid<Body>,
+        crosses: &SpatialGrid<Cross>,
+        selection: &Selection,
+        palette: &mut Palette,
+        user_constants: &UserConstants,
+    ) -> Option<Intervention> {
+        Some(match self.get_tool() {
+            Tool::Plants => Intervention::PlantPatch {
+                x:          pos.x,
+                y:          pos.y,
+                radius:     self.brush_radius,
+                n:          (PI
+                    * self.brush_radius.powi(2)
+                    * EDITOR_PLANTS_DENSITY)
+                    .ceil() as usize,
+                plant_kind: PlantKind::ALL[self.plant_kind_i],
+            },
+            Tool::Body => {
+                let (body_type, color) = self.get_body_type(
+                    bodies,
+                    crosses,
+                    palette,
+                    user_constants,
+                );
+
+                Intervention::PlaceBody {
+                    x: pos.x,
+                    y: pos.y,
+                    eating_strategy: EatingStrategy::ALL
+                        [self.eating_strategy_i],
+                    body_type,
+                    color,
+                    skills: self.skills.iter().collect(),
+                    viruses: self.get_viruses(),
+                    energy: None,
+                    speed: None,
+                    vision_distance: None,
+                    division_threshold: None,
+                }
+            }
+            Tool::Clone => {
+                let body = selection.get_body(bodies)?;
+                let [r, g, b, _]: [u8; 4] = body.color.into();
+
+                Intervention::PlaceBody {
+                    x:                  pos.x,
+                    y:                  pos.y,
+                    eating_strategy:    body.eating_strategy,
+                    body_type:          body.body_type,
+                    color:              [r, g, b],
+                    skills:             body.skills.iter().collect(),
+                    viruses:            body.viruses.keys().collect(),
+                    energy:             Some(body.energy),
+                    speed:              Some(body.initial_speed),
+                    vision_distance:    Some(
+                        body.initial_vision_distance,
+                    ),
+                    division_threshold: Some(body.division_threshold),
+                }
+            }
+            Tool::Erase => Intervention::Meteor {
+                x:      pos.x,
+                y:      pos.y,
+                radius: self.brush_radius,
+            },
+            Tool::Cross => {
+                let (body_type, color) = self.get_body_type(
+                    bodies,
+                    crosses,
+                    palette,
+                    user_constants,
+                );
+
+                Intervention::PlaceCross {
+                    x: pos.x,
+                    y: pos.y,
+                    energy: user_constants
+                        .average_energy_omnivorous_herbivorous,
+                    body_type,
+                    color,
+                    viruses: self.get_viruses(),
+                }
+            }
+        })
+    }
+
+    /// Show the area the brush covers around the cursor at `pos`.
+    pub fn draw_brush(&self, pos: Vec2, batch: &mut Batch) {
+        if self.active
+            && matches!(self.get_tool(), Tool::Plants | Tool::Erase)
+        {
+            batch.circle_lines(
+                pos,
+                self.brush_radius,
+                EDITOR_BRUSH_THICKNESS,
+                Color {
+                    a: EDITOR_BRUSH_ALPHA,
+                    ..WHITE
+                },
+            );
+        }
+    }
+
+    /// Has to be drawn with the default camera.
+    pub fn draw_panel(&self) {
+        if !self.active {
+            return;
+        }
+
+        let tool = self.get_tool();
+        let mut lines = vec![
+            "editor (P to close, clicks place)".to_owned(),
+            format!("[Q] tool: {:?}", tool),
+        ];
+
+        match tool {
+            Tool::Plants => lines.push(format!(
+                "[A] plant kind: {:?}",
+                PlantKind::ALL[self.plant_kind_i]
+            )),
+            Tool::Body => lines.push(format!(
+                "[A] eating strategy: {:?}",
+                EatingStrategy::ALL[self.eating_strategy_i]
+            )),
+            Tool::Clone => lines.push(
+                "copies the selected body (Shift+click selects)"
+                    .to_owned(),
+            ),
+            _ => (),
+        }
+
+        if matches!(tool, Tool::Body | Tool::Cross) {
+            lines.push(format!(
+                "[Y] body type: {}",
+                match self.body_type {
+                    Some(body_type) => body_type.to_string(),
+                    None => "new".to_owned(),
+                }
+            ));
+            lines.push(format!(
+                "[6] viruses: {}",
+                match self.get_viruses() {
+                    viruses if viruses.is_empty() =>
+                        "none".to_owned(),
+                    viruses => format!("{:?}", viruses),
+                }
+            ));
+        }
+
+        if tool == Tool::Body {
+            let skill = Skill::ALL[self.skill_i];
+
+            lines.push(format!(
+                "[4] skill: {:?} [5] {}",
+                skill,
+                if self.skills.contains(skill) {
+                    "on"
+                } else {
+                    "off"
+                }
+            ));
+            lines.push(format!("skills: {}", self.skills.len()));
+        }
+
+        if matches!(tool, Tool::Plants | Tool::Erase) {
+            lines.push(format!(
+                "[Z]/[X] brush radius: {:.0}",
+                self.brush_radius
+            ));
+        }
+
+        draw_text_panel(
+            &lines,
+            Vec2::new(
+                (screen_width() - get_text_panel_size(&lines).x)
+                    / 2.0,
+                PANEL_TOP,
+            ),
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use crate::{
+    constants::*, draw_text_panel, get_text_panel_size, Body,
+    EatingStrategy, Skill, SpatialGrid, Status, Virus,
+};
+use macroquad::prelude::{
+    is_key_pressed, screen_width, Color, KeyCode, Vec2,
+};
+use serde_derive::Deserialize;
+use std::fmt::Debug;
+
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+/// What a body can be doing, without the details of `Status`.
+pub enum StatusKind {
+    FollowingTarget,
+    EscapingBody,
+    Walking,
+    Idle,
+}
+
+impl StatusKind {
+    pub const ALL: [Self; 4] = [
+        Self::FollowingTarget,
+        Self::EscapingBody,
+        Self::Walking,
+        Self::Idle,
+    ];
+
+    #[inline(always)]
+    fn matches(&self, status: &Status) -> bool {
+        matches!(
+            (self, status),
+            (
+                StatusKind::FollowingTarget,
+                Status::FollowingTarget(..)
+            ) | (StatusKind::EscapingBody, Status::EscapingBody(..))
+                | (StatusKind::Walking, Status::Walking(..))
+                | (StatusKind::Idle, Status::Idle)
+        )
+    }
+}
+
+#[derive(Default, Debug, Clone, Deserialize)]
+/// Which bodies are shown. A criterion that's `None` lets every body
+/// through.
+pub struct BodyFilter {
+    pub body_type:       Option<u32>,
+    pub eating_strategy: Option<EatingStrategy>,
+    pub with_skill:      Option<Skill>,
+    pub without_skill:   Option<Skill>,
+    /// The virus the body is infected with.
+    pub virus:           Option<Virus>,
+    pub status:          Option<StatusKind>,
+    /// Whether the bodies that don't match are dimmed instead of hidden.
+    pub dim:             bool,
+}
+
+#[inline(always)]
+/// The value after `value` within `all`, and `None` after the last one.
+fn next_option<T: Copy + PartialEq>(
+    value: Option<T>,
+    all: &[T],
+) -> Option<T> {
+    match value {
+        None => all.first().copied(),
+        Some(value) => all
+            .iter()
+            .position(|other| *other == value)
+            .and_then(|i| all.get(i + 1))
+            .copied(),
+    }
+}
+
+#[inline(always)]
+fn describe<T: Debug>(value: Option<T>) -> String {
+    match value {
+        Some(value) => format!("{:?}", value),
+        None => "any".to_owned(),
+    }
+}
+
+impl BodyFilter {
+    #[inline(always)]
+    pub fn is_active(&self) -> bool {
+        self.body_type.is_some()
+            || self.eating_strategy.is_some()
+            || self.with_skill.is_some()
+            || self.without_skill.is_some()
+            || self.virus.is_some()
+            || self.status.is_some()
+    }
+
+    #[inline(always)]
+    pub fn matches(&self, body: &Body) -> bool {
+        self.body_type
+            .is_none_or(|body_type| body.body_type == body_type)
+            && self.eating_strategy.is_none_or(|eating_strategy| {
+                body.eating_strategy == eating_strategy
+            })
+            && self
+                .with_skill
+                .is_none_or(|skill| body.skills.contains(skill))
+            && self
+                .without_skill
+                .is_none_or(|skill| !body.skills.contains(skill))
+            && self
+                .virus
+                .is_none_or(|virus| body.viruses.contains(virus))
+            && self
+                .status
+                .is_none_or(|status| status.matches(&body.status))
+    }
+
+    #[inline(always)]
+    /// The color the body is drawn with, `None` if it's hidden.
+    pub fn get_color(
+        &self,
+        body: &Body,
+        color: Color,
+    ) -> Option<Color> {
+        if self.matches(body) {
+            Some(color)
+        } else if self.dim {
+            Some(Color {
+                a: FILTER_DIM_ALPHA,
+                ..color
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// The filter changed with the keys, starting from the one from the
+/// config.
+pub struct ViewFilter {
+    pub filter: BodyFilter,
+    show_panel: bool,
+}
+
+impl ViewFilter {
+    pub fn new(filter: BodyFilter) -> Self {
+        Self {
+            filter,
+            show_panel: false,
+        }
+    }
+
+    /// Start from the filter of the config again, dropping the changes.
+    pub fn reload(&mut self, filter: &BodyFilter) {
+        self.filter = filter.clone();
+    }
+
+    /// Open or close the panel with Tab, and change the criteria with
+    /// the keys shown on it while it's open.
+    pub fn handle_input(
+        &mut self,
+        bodies: &SpatialGrid<Body>,
+        config_filter: &BodyFilter,
+    ) {
+        if is_key_pressed(KeyCode::Tab) {
+            self.show_panel = !self.show_panel;
+        }
+
+        if !self.show_panel {
+            return;
+        }
+
+        let filter = &mut self.filter;
+
+        if is_key_pressed(KeyCode::T) {
+            let mut body_types = bodies
+                .values()
+                .map(|body| body.body_type)
+                .collect::<Vec<_>>();
+            body_types.sort_unstable();
+            body_types.dedup();
+
+            filter.body_type =
+                next_option(filter.body_type, &body_types);
+        }
+
+        if is_key_pressed(KeyCode::E) {
+            filter.eating_strategy = next_option(
+                filter.eating_strategy,
+                &EatingStrategy::ALL,
+            );
+        }
+
+        if is_key_pressed(KeyCode::S) {
+            filter.with_skill =
+                next_option(filter.with_skill, &Skill::ALL);
+        }
+
+        if is_key_pressed(KeyCode::W) {
+            filter.without_skill =
+                next_option(filter.without_skill, &Skill::ALL);
+        }
+
+        if is_key_pressed(KeyCode::I) {
+            filter.virus = next_option(filter.virus, &Virus::ALL);
+        }
+
+        if is_key_pressed(KeyCode::U) {
+            filter.status =
+                next_option(filter.status, &StatusKind::ALL);
+        }
+
+        if is_key_pressed(KeyCode::D) {
+            filter.dim = !filter.dim;
+        }
+
+        if is_key_pressed(KeyCode::R) {
+            *filter = config_filter.clone();
+        }
+    }
+
+    /// Has to be drawn with the default camera.
+    pub fn draw_panel(&self) {
+        if !self.show_panel {
+            return;
+        }
+
+        let filter = &self.filter;
+
+        let lines = [
+            "filters (Tab to close)".to_owned(),
+            format!("[T] body type: {}", describe(filter.body_type)),
+            format!(
+                "[E] eating strategy: {}",
+                describe(filter.eating_strategy)
+            ),
+            format!(
+                "[S] with skill: {}",
+                describe(filter.with_skill)
+            ),
+            format!(
+                "[W] without skill: {}",
+                describe(filter.without_skill)
+            ),
+            format!("[I] virus: {}", describe(filter.virus)),
+            format!("[U] status: {}", describe(filter.status)),
+            format!(
+                "[D] the others are {}",
+                if filter.dim { "dimmed" } else { "hidden" }
+            ),
+            "[R] reset to the config".to_owned(),
+        ];
+
+        draw_text_panel(
+            &lines,
+            Vec2::new(
+                screen_width() - get_text_panel_size(&lines).x,
+                FILTER_PANEL_TOP,
+            ),
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use crate::{
+    constants::*, draw_legend, get_gradient_color, Body,
+    EatingStrategy, Plant, SpatialGrid, AREA_SIZE, CELLS,
+};
+use macroquad::prelude::{
+    draw_texture_ex, Color, DrawTextureParams, FilterMode, Image,
+    Texture2D, Vec2, BLANK, WHITE,
+};
+use std::collections::VecDeque;
+
+#[derive(Clone, Copy, PartialEq)]
+/// What the cells are colored by.
+pub enum Overlay {
+    Plants,
+    Bodies(EatingStrategy),
+    Viruses,
+    Energy,
+    Speed,
+    Deaths,
+}
+
+impl Overlay {
+    pub const ALL: [Self; 8] = [
+        Self::Plants,
+        Self::Bodies(EatingStrategy::Omnivorous),
+        Self::Bodies(EatingStrategy::Herbivorous),
+        Self::Bodies(EatingStrategy::Carnivorous),
+        Self::Viruses,
+        Self::Energy,
+        Self::Speed,
+        Self::Deaths,
+    ];
+
+    #[inline(always)]
+    fn get_title(&self) -> String {
+        match self {
+            Overlay::Plants => "plants per cell".to_owned(),
+            Overlay::Bodies(eating_strategy) => {
+                format!("{:?} bodies per cell", eating_strategy)
+            }
+            Overlay::Viruses => "infected bodies (%)".to_owned(),
+            Overlay::Energy => "average energy".to_owned(),
+            Overlay::Speed => "average speed".to_owned(),
+            Overlay::Deaths => format!(
+                "deaths in the last {} ticks",
+                HEATMAP_DEATHS_TICKS_N
+            ),
+        }
+    }
+
+    #[inline(always)]
+    /// Whether the values within a cell are averaged instead of summed.
+    fn is_average(&self) -> bool {
+        matches!(
+            self,
+            Overlay::Viruses | Overlay::Energy | Overlay::Speed
+        )
+    }
+}
+
+/// The cells colored by what's within them, so that it can be seen
+/// where things happen.
+pub struct Heatmap {
+    /// The index of the overlay within `Overlay::ALL`, `None` if no
+    /// overlay is shown.
+    overlay_i: Option<usize>,
+    /// When and where the bodies have died recently.
+    deaths:    VecDeque<(u64, Vec2)>,
+    /// The sum of the values and the number of them within every cell.
+    cells:     Vec<(f32, f32)>,
+    /// The lowest and the highest values of the cells.
+    range:     (f32, f32),
+    image:     Image,
+    texture:   Texture2D,
+}
+
+impl Heatmap {
+    pub fn new() -> Self {
+        let image = Image::gen_image_color(
+            CELLS.columns as u16,
+            CELLS.rows as u16,
+            BLANK,
+        );
+        let texture = Texture2D::from_image(&image);
+        texture.set_filter(FilterMode::Nearest);
+
+        Self {
+            overlay_i: None,
+            deaths: VecDeque::new(),
+            cells: vec![(0.0, 0.0); CELLS.columns * CELLS.rows],
+            range: (0.0, 0.0),
+            image,
+            texture,
+        }
+    }
+
+    #[inline(always)]
+    /// Show the next overlay, or none after the last one.
+    pub fn next_overlay(&mut self) {
+        self.overlay_i = match self.overlay_i {
+            None => Some(0),
+            Some(i) if i + 1 < Overlay::ALL.len() => Some(i + 1),
+            Some(_) => None,
+        };
+    }
+
+    #[inline(always)]
+    fn get_overlay(&self) -> Option<Overlay> {
+        self.overlay_i.map(|i| Overlay::ALL[i])
+    }
+
+    #[inline(always)]
+    pub fn record_death(&mut self, tick: u64, pos: Vec2) {
+        self.deaths.push_back((tick, pos));
+        self.forget_old_deaths(tick);
+    }
+
+    #[inline(always)]
+    fn forget_old_deaths(&mut self, tick: u64) {
+        while self.deaths.front().is_some_and(|(death_tick, _)| {
+            death_tick + HEATMAP_DEATHS_TICKS_N < tick
+        }) {
+            self.deaths.pop_front();
+        }
+    }
+
+    #[inline(always)]
+    fn add(&mut self, pos: Vec2, value: f32) {
+        let cell = CELLS.get_cell_by_pos(pos);
+        let (sum, n) = &mut self.cells[cell.i.min(CELLS.rows - 1)
+            * CELLS.columns
+            + cell.j.min(CELLS.columns - 1)];
+
+        *sum += value;
+        *n += 1.0;
+    }
+
+    pub fn draw(
+        &mut self,
+        plants: &SpatialGrid<Plant>,
+        bodies: &SpatialGrid<Body>,
+        tick: u64,
+    ) {
+        let Some(overlay) = self.get_overlay() else {
+            return;
+        };
+
+        self.cells.fill((0.0, 0.0));
+
+        match overlay {
+            Overlay::Plants => {
+                for plant in plants.values() {
+                    self.add(plant.pos, 1.0);
+                }
+            }
+            Overlay::Bodies(eating_strategy) => {
+                for body in bodies.values() {
+                    if body.eating_strategy == eating_strategy {
+                        self.add(body.pos, 1.0);
+                    }
+                }
+            }
+            Overlay::Viruses => {
+                for body in bodies.values() {
+                    self.add(
+                        body.pos,
+                        if body.viruses.is_empty() {
+                            0.0
+                        } else {
+                            100.0
+                        },
+                    );
+                }
+            }
+            Overlay::Energy => {
+                for body in bodies.values() {
+                    self.add(body.pos, body.energy);
+                }
+            }
+            Overlay::Speed => {
+                for body in bodies.values() {
+                    self.add(body.pos, body.speed);
+                }
+            }
+            Overlay::Deaths => {
+                self.forget_old_deaths(tick);
+
+                for i in 0..self.deaths.len() {
+                    self.add(self.deaths[i].1, 1.0);
+                }
+            }
+        }
+
+        let is_average = overlay.is_average();
+        let get_value = |(sum, n): &(f32, f32)| {
+            (*n > 0.0)
+                .then(|| if is_average { sum / n } else { *sum })
+        };
+
+        self.range = match overlay {
+            Overlay::Viruses => (0.0, 100.0),
+            _ => {
+                let (min, max) =
+                    self.cells.iter().filter_map(get_value).fold(
+                        (f32::MAX, f32::MIN),
+                        |(min, max), value| {
+                            (min.min(value), max.max(value))
+                        },
+                    );
+
+                if min > max {
+                    // Nothing to show
+                    (0.0, 0.0)
+                } else if is_average {
+                    (min, max)
+                } else {
+                    // Counting starts from nothing
+                    (0.0, max)
+                }
+            }
+        };
+
+        let (min, max) = self.range;
+
+        for (cell, pixel) in self
+            .cells
+            .iter()
+            .zip(self.image.bytes.chunks_exact_mut(4))
+        {
+            let color = match get_value(cell) {
+                Some(value) => {
+                    let t = if max > min {
+                        (value - min) / (max - min)
+                    } else {
+                        1.0
+                    };
+
+                    Color {
+                        a: HEATMAP_ALPHA,
+                        ..get_gradient_color(t)
+                    }
+                }
+                None => BLANK,
+            };
+
+            let bytes: [u8; 4] = color.into();
+            pixel.copy_from_slice(&bytes);
+        }
+
+        self.texture.update(&self.image);
+
+        draw_texture_ex(
+            &self.texture,
+            0.0,
+            0.0,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(*AREA_SIZE),
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Has to be drawn with the default camera.
+    pub fn draw_legend(&self) {
+        if let Some(overlay) = self.get_overlay() {
+            draw_legend(
+                &overlay.get_title(),
+                self.range.0,
+                self.range.1,
+                HEATMAP_LEGEND_ROW,
+            );
+        }
+    }
+}
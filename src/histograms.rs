@@ -0,0 +1,256 @@
+use crate::{constants::*, Body, EatingStrategy, Skill, SpatialGrid};
+use macroquad::prelude::{
+    draw_rectangle, draw_text, measure_text, screen_width, Color,
+    Rect, WHITE,
+};
+
+type GetTrait = fn(&Body) -> f32;
+
+/// The traits the histograms are drawn for.
+const TRAITS: [(&str, GetTrait); 5] = [
+    ("speed", |body| body.speed),
+    ("vision distance", |body| body.vision_distance),
+    ("division threshold", |body| body.division_threshold),
+    ("energy", |body| body.energy),
+    ("lifespan", |body| body.lifespan),
+];
+
+#[inline(always)]
+fn format_value(value: f32) -> String {
+    if value.abs() >= 10.0 {
+        format!("{value:.0}")
+    } else {
+        format!("{value:.2}")
+    }
+}
+
+/// How the traits and the skills are spread among the living bodies.
+pub struct Histograms {
+    pub show: bool,
+}
+
+impl Histograms {
+    pub fn new() -> Self {
+        Self { show: false }
+    }
+
+    /// Has to be drawn with the default camera.
+    pub fn draw(&self, bodies: &SpatialGrid<Body>) {
+        if !self.show {
+            return;
+        }
+
+        let cell_width = HISTOGRAM_WIDTH + PANEL_PADDING;
+        let cell_height = PANEL_FONT_SIZE as f32 * 2.0
+            + PANEL_LINE_GAP * 2.0
+            + HISTOGRAM_HEIGHT
+            + PANEL_PADDING;
+        let rows_n = TRAITS.len().div_ceil(HISTOGRAM_COLUMNS_N);
+
+        let width = cell_width * HISTOGRAM_COLUMNS_N as f32
+            + SKILL_CHART_WIDTH
+            + PANEL_PADDING * 2.0;
+        let height = PANEL_FONT_SIZE as f32
+            + PANEL_LINE_GAP
+            + cell_height * rows_n as f32
+            + PANEL_PADDING;
+        let x = (screen_width() - width) / 2.0;
+        let y = PANEL_PADDING;
+
+        draw_rectangle(
+            x,
+            y,
+            width,
+            height,
+            Color::new(0.0, 0.0, 0.0, PANEL_ALPHA),
+        );
+
+        // The colors of the eating strategies
+        let mut header_x = x + PANEL_PADDING;
+        let header_y = y + PANEL_PADDING + PANEL_FONT_SIZE as f32;
+
+        for eating_strategy in EatingStrategy::ALL {
+            let text = format!("{:?}", eating_strategy);
+
+            draw_text(
+                &text,
+                header_x,
+                header_y,
+                PANEL_FONT_SIZE as f32,
+                eating_strategy.get_color(),
+            );
+
+            header_x +=
+                measure_text(&text, None, PANEL_FONT_SIZE, 1.0).width
+                    + PANEL_PADDING * 2.0;
+        }
+
+        let top = header_y + PANEL_LINE_GAP;
+
+        for (i, (name, get_trait)) in TRAITS.iter().enumerate() {
+            let column = i % HISTOGRAM_COLUMNS_N;
+            let row = i / HISTOGRAM_COLUMNS_N;
+
+            draw_histogram(
+                name,
+                *get_trait,
+                bodies,
+                Rect::new(
+                    x + PANEL_PADDING + cell_width * column as f32,
+                    top + cell_height * row as f32,
+                    HISTOGRAM_WIDTH,
+                    cell_height,
+                ),
+            );
+        }
+
+        draw_skill_chart(
+            bodies,
+            Rect::new(
+                x + PANEL_PADDING
+                    + cell_width * HISTOGRAM_COLUMNS_N as f32,
+                top,
+                SKILL_CHART_WIDTH,
+                cell_height * rows_n as f32,
+            ),
+        );
+    }
+}
+
+/// The bars are stacked by the eating strategy.
+fn draw_histogram(
+    name: &str,
+    get_trait: GetTrait,
+    bodies: &SpatialGrid<Body>,
+    rect: Rect,
+) {
+    let (min, max) = bodies
+        .values()
+        .map(get_trait)
+        .fold((f32::MAX, f32::MIN), |(min, max), value| {
+            (min.min(value), max.max(value))
+        });
+
+    let title_y = rect.y + PANEL_FONT_SIZE as f32;
+
+    draw_text(name, rect.x, title_y, PANEL_FONT_SIZE as f32, WHITE);
+
+    if min > max {
+        return;
+    }
+
+    let mut bins =
+        [[0usize; EatingStrategy::ALL.len()]; HISTOGRAM_BINS_N];
+
+    for body in bodies.values() {
+        let bin = if max > min {
+            ((get_trait(body) - min) / (max - min)
+                * HISTOGRAM_BINS_N as f32) as usize
+        } else {
+            0
+        }
+        .min(HISTOGRAM_BINS_N - 1);
+
+        bins[bin][body.eating_strategy as usize] += 1;
+    }
+
+    let highest_bin = bins
+        .iter()
+        .map(|bin| bin.iter().sum::<usize>())
+        .max()
+        .unwrap()
+        .max(1);
+
+    let bar_width = rect.w / HISTOGRAM_BINS_N as f32;
+    let bottom = title_y + PANEL_LINE_GAP + HISTOGRAM_HEIGHT;
+
+    for (i, bin) in bins.iter().enumerate() {
+        let mut bar_bottom = bottom;
+
+        for (eating_strategy, n) in
+            EatingStrategy::ALL.iter().zip(bin)
+        {
+            let bar_height =
+                HISTOGRAM_HEIGHT * *n as f32 / highest_bin as f32;
+
+            draw_rectangle(
+                rect.x + bar_width * i as f32,
+                bar_bottom - bar_height,
+                bar_width - 1.0,
+                bar_height,
+                eating_strategy.get_color(),
+            );
+
+            bar_bottom -= bar_height;
+        }
+    }
+
+    // The range the bins cover
+    let values_y = bottom + PANEL_LINE_GAP + PANEL_FONT_SIZE as f32;
+    let max_text = format_value(max);
+    let measured =
+        measure_text(&max_text, None, PANEL_FONT_SIZE, 1.0);
+
+    draw_text(
+        &format_value(min),
+        rect.x,
+        values_y,
+        PANEL_FONT_SIZE as f32,
+        WHITE,
+    );
+    draw_text(
+        &max_text,
+        rect.x + rect.w - measured.width,
+        values_y,
+        PANEL_FONT_SIZE as f32,
+        WHITE,
+    );
+}
+
+/// How many living bodies have each skill.
+fn draw_skill_chart(bodies: &SpatialGrid<Body>, rect: Rect) {
+    let mut skills_n = [0usize; Skill::ALL.len()];
+
+    for body in bodies.values() {
+        for skill in body.skills.iter() {
+            skills_n[skill as usize] += 1;
+        }
+    }
+
+    let title_y = rect.y + PANEL_FONT_SIZE as f32;
+
+    draw_text(
+        "bodies with the skill",
+        rect.x,
+        title_y,
+        PANEL_FONT_SIZE as f32,
+        WHITE,
+    );
+
+    let row_height =
+        (rect.h - PANEL_FONT_SIZE as f32 - PANEL_LINE_GAP)
+            / Skill::ALL.len() as f32;
+    let font_size = (row_height - 2.0).min(PANEL_FONT_SIZE as f32);
+    let bodies_n = bodies.len().max(1);
+
+    for (i, (skill, n)) in Skill::ALL.iter().zip(skills_n).enumerate()
+    {
+        let row_y = title_y + PANEL_LINE_GAP + row_height * i as f32;
+
+        draw_rectangle(
+            rect.x,
+            row_y + 1.0,
+            rect.w * n as f32 / bodies_n as f32,
+            row_height - 2.0,
+            SKILL_CHART_BAR_COLOR,
+        );
+
+        draw_text(
+            &format!("{:?} {}", skill, n),
+            rect.x + 2.0,
+            row_y + row_height - 4.0,
+            font_size,
+            WHITE,
+        );
+    }
+}
@@ -0,0 +1,312 @@
+use crate::{
+    constants::*, Batch, Body, BodyId, ObjectType, SpatialGrid,
+    Status, Topology, UserConstants, Zoom,
+};
+use macroquad::prelude::{
+    draw_rectangle, draw_text, measure_text, Color, Vec2, WHITE,
+};
+
+#[derive(Default)]
+/// The body that's been clicked on to be inspected.
+pub struct Selection {
+    pub body_id:  Option<BodyId>,
+    /// Whether the camera stays centered on the body.
+    pub follow:   bool,
+    /// What the body has divided into, so that one of the children can
+    /// be followed once the body is gone.
+    pub children: Vec<BodyId>,
+}
+
+impl Selection {
+    #[inline(always)]
+    pub fn get_body<'a>(
+        &self,
+        bodies: &'a SpatialGrid<Body>,
+    ) -> Option<&'a Body> {
+        bodies.get(&self.body_id?)
+    }
+
+    /// Select the body under `pos` and return whether there's been one.
+    pub fn select_at(
+        &mut self,
+        pos: Vec2,
+        bodies: &SpatialGrid<Body>,
+    ) -> bool {
+        match bodies.k_nearest(pos, 1, Topology::Bounded).first() {
+            Some((body_id, body))
+                if body.pos.distance(pos) <= OBJECT_RADIUS * 2.0 =>
+            {
+                *self = Self {
+                    body_id: Some(**body_id),
+                    ..Default::default()
+                };
+
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Follow the body, or one of its children if it's gone. Return
+    /// whether there's a body to follow.
+    pub fn follow(&mut self, bodies: &SpatialGrid<Body>) -> bool {
+        if self.get_body(bodies).is_none() {
+            match self
+                .children
+                .iter()
+                .find(|child_id| bodies.get(child_id).is_some())
+            {
+                Some(child_id) => {
+                    *self = Self {
+                        body_id: Some(*child_id),
+                        ..Default::default()
+                    }
+                }
+                None => return false,
+            }
+        }
+
+        self.follow = true;
+
+        true
+    }
+
+    /// Circle the body to make it stand out.
+    pub fn draw_highlight(
+        &self,
+        bodies: &SpatialGrid<Body>,
+        zoom: &Zoom,
+        alpha: f32,
+        batch: &mut Batch,
+    ) {
+        if let Some(body) = self.get_body(bodies) {
+            // The size on the screen doesn't depend on the zoom
+            let pixel = OBJECT_RADIUS / zoom.scale;
+
+            batch.circle_lines(
+                body.get_drawn_pos(alpha),
+                OBJECT_RADIUS + SELECTION_RING_GAP * pixel,
+                2.0 * pixel,
+                WHITE,
+            );
+        }
+    }
+
+    /// Everything about the body. Has to be drawn with the default
+    /// camera.
+    pub fn draw_panel(
+        &self,
+        bodies: &SpatialGrid<Body>,
+        user_constants: &UserConstants,
+    ) {
+        if self.body_id.is_none() {
+            return;
+        }
+
+        let lines = match self.get_body(bodies) {
+            Some(body) => self.get_body_lines(body, user_constants),
+            None => {
+                let mut lines = vec![if self.children.is_empty() {
+                    "The body has died.".to_owned()
+                } else {
+                    format!(
+                        "The body has divided into {} bodies.",
+                        self.children.len()
+                    )
+                }];
+
+                if self
+                    .children
+                    .iter()
+                    .any(|child_id| bodies.get(child_id).is_some())
+                {
+                    lines.push(
+                        "Press F to follow one of them.".to_owned(),
+                    );
+                }
+
+                lines
+            }
+        };
+
+        draw_text_panel(&lines, Vec2::new(0.0, PANEL_TOP));
+    }
+
+    fn get_body_lines(
+        &self,
+        body: &Body,
+        user_constants: &UserConstants,
+    ) -> Vec<String> {
+        let species = (body.body_type as usize)
+            .checked_sub(1)
+            .and_then(|i| user_constants.species.get(i));
+
+        // What the body will have when it reaches the target, or after
+        // the next tick if there's no target
+        let (projected_energy_label, ticks) = match body.status {
+            Status::FollowingTarget(_, target_pos, _) => (
+                "energy on arrival",
+                body.pos.distance(target_pos) / body.speed,
+            ),
+            _ => ("energy after a tick", 1.0),
+        };
+
+        let mut lines = vec![
+            match species {
+                Some(species) => format!(
+                    "body type = {} ({})",
+                    body.body_type, species.name
+                ),
+                None => format!("body type = {}", body.body_type),
+            },
+            format!("eating strategy = {:?}", body.eating_strategy),
+            format!("generation = {}", body.generation),
+            format!(
+                "age = {} ticks ({} s)",
+                body.age,
+                body.age / SECOND_TICKS_N
+            ),
+            format!("lifespan = {} s", body.lifespan as usize),
+            format!("energy = {}", body.energy as usize),
+            format!(
+                "division threshold = {}",
+                body.division_threshold as usize
+            ),
+            format!(
+                "{projected_energy_label} = {}",
+                (body.energy
+                    - body.get_spent_energy(ticks, user_constants))
+                    as isize
+            ),
+            format!(
+                "speed = {:.2} (base {:.2})",
+                body.speed, body.initial_speed
+            ),
+            format!(
+                "vision distance = {:.1} (base {:.1})",
+                body.vision_distance, body.initial_vision_distance
+            ),
+            format!("status = {}", get_status_description(body)),
+        ];
+
+        if let Status::FollowingTarget(_, target_pos, target_type) =
+            body.status
+        {
+            lines.push(format!(
+                "target = {} {:.0} away",
+                match target_type {
+                    ObjectType::Body => "body",
+                    ObjectType::Plant => "plant",
+                    ObjectType::Cross => "cross",
+                },
+                body.pos.distance(target_pos)
+            ));
+        }
+
+        lines.push(if body.skills.is_empty() {
+            "skills = none".to_owned()
+        } else {
+            "skills:".to_owned()
+        });
+        lines.extend(
+            body.skills.iter().map(|skill| format!("  {:?}", skill)),
+        );
+
+        lines.push(if body.viruses.is_empty() {
+            "viruses = none".to_owned()
+        } else {
+            "viruses:".to_owned()
+        });
+        lines.extend(body.viruses.iter().map(
+            |(virus, energy_spent_for_healing)| {
+                format!(
+                    "  {:?} (healed {}/{})",
+                    virus,
+                    energy_spent_for_healing as usize,
+                    virus.get_heal_energy(user_constants) as usize
+                )
+            },
+        ));
+
+        lines.push(if self.follow {
+            "following (F to stop)".to_owned()
+        } else {
+            "F to follow".to_owned()
+        });
+
+        lines
+    }
+}
+
+#[inline(always)]
+fn get_status_description(body: &Body) -> String {
+    match body.status {
+        Status::FollowingTarget(_, _, ObjectType::Body) => {
+            "chasing a body".to_owned()
+        }
+        Status::FollowingTarget(_, _, ObjectType::Plant) => {
+            "going to a plant".to_owned()
+        }
+        Status::FollowingTarget(_, _, ObjectType::Cross) => {
+            "going to a cross".to_owned()
+        }
+        Status::EscapingBody(_, body_type) => {
+            format!("escaping a body of type {body_type}")
+        }
+        // The status only has the step the body makes every tick
+        Status::Walking(pos_deviation) => format!(
+            "walking at {:.0} degrees",
+            pos_deviation
+                .y
+                .atan2(pos_deviation.x)
+                .to_degrees()
+                .rem_euclid(360.0)
+        ),
+        Status::Idle => "idle".to_owned(),
+        Status::Cross => "dead".to_owned(),
+        Status::Undefined => "deciding".to_owned(),
+    }
+}
+
+/// The size of the panel `draw_text_panel` draws for the lines.
+pub fn get_text_panel_size(lines: &[String]) -> Vec2 {
+    let width = lines
+        .iter()
+        .map(|line| {
+            measure_text(line, None, PANEL_FONT_SIZE, 1.0).width
+        })
+        .fold(0.0, f32::max);
+
+    Vec2::new(
+        width + PANEL_PADDING * 2.0,
+        (PANEL_FONT_SIZE as f32 + PANEL_LINE_GAP)
+            * lines.len() as f32
+            + PANEL_PADDING * 2.0,
+    )
+}
+
+/// Draw the lines on a translucent background with the top left corner
+/// at `pos` on the screen.
+pub fn draw_text_panel(lines: &[String], pos: Vec2) {
+    let line_height = PANEL_FONT_SIZE as f32 + PANEL_LINE_GAP;
+    let size = get_text_panel_size(lines);
+
+    draw_rectangle(
+        pos.x,
+        pos.y,
+        size.x,
+        size.y,
+        Color::new(0.0, 0.0, 0.0, PANEL_ALPHA),
+    );
+
+    for (i, line) in lines.iter().enumerate() {
+        draw_text(
+            line,
+            pos.x + PANEL_PADDING,
+            pos.y + PANEL_PADDING + line_height * (i as f32 + 1.0)
+                - PANEL_LINE_GAP,
+            PANEL_FONT_SIZE as f32,
+            WHITE,
+        );
+    }
+}
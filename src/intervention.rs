@@ -0,0 +1,509 @@
+use crate::{
+    clamp_pos, constants::*, get_color, random_pos_in_circle,
+    set_constant, Body, Cross, EatingStrategy, IdCounter, Plant,
+    PlantKind, Skill, SpatialGrid, Topology, UserConstants, Virus,
+};
+use macroquad::prelude::{Circle, KeyCode, Vec2};
+use rand::{rngs::StdRng, seq::IteratorRandom};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    fs::{read_to_string, File},
+    io::Write,
+    process::exit,
+};
+use toml::{from_str, to_string, Value};
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+/// A user-triggered perturbation of the evolution.
+pub enum Intervention {
+    /// Kill every body, plant and cross within the radius.
+    Meteor {
+        x:      f32,
+        y:      f32,
+        radius: f32,
+    },
+    /// Spawn `n` plants within the radius.
+    PlantBloom {
+        x:      f32,
+        y:      f32,
+        radius: f32,
+        n:      usize,
+    },
+    /// Infect every body within the radius with the virus.
+    VirusRelease {
+        x:      f32,
+        y:      f32,
+        radius: f32,
+        virus:  Virus,
+    },
+    /// Kill every body of the type.
+    Cull { body_type: u32 },
+    /// Spawn `n` plants of the kind within the radius.
+    PlantPatch {
+        x:          f32,
+        y:          f32,
+        radius:     f32,
+        n:          usize,
+        plant_kind: PlantKind,
+    },
+    /// Place a body. Every property that isn't specified is generated
+    /// the way it's done for the 1st generation.
+    PlaceBody {
+        x:                  f32,
+        y:                  f32,
+        eating_strategy:    EatingStrategy,
+        body_type:          u32,
+        /// RGB.
+        color:              [u8; 3],
+        #[serde(default)]
+        skills:             Vec<Skill>,
+        #[serde(default)]
+        viruses:            Vec<Virus>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        energy:             Option<f32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        speed:              Option<f32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        vision_distance:    Option<f32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        division_threshold: Option<f32>,
+    },
+    /// Place a cross that can be eaten or infect the bodies eating it.
+    PlaceCross {
+        x:         f32,
+        y:         f32,
+        energy:    f32,
+        body_type: u32,
+        /// RGB.
+        color:     [u8; 3],
+        #[serde(default)]
+        viruses:   Vec<Virus>,
+    },
+    /// Change a value of the config from the tick on the way the
+    /// settings panel does.
+    SetConstant {
+        section: String,
+        key:     String,
+        value:   Value,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct ScheduledIntervention {
+    tick:         u64,
+    #[serde(flatten)]
+    intervention: Intervention,
+}
+
+#[derive(Deserialize)]
+struct ScheduleData {
+    /// The seed the run has to be repeated with.
+    seed:   Option<u64>,
+    #[serde(default)]
+    events: Vec<ScheduledIntervention>,
+}
+
+/// The file every intervention is written to in the format of the
+/// interventions file to make it possible to replay the run.
+pub struct InterventionLog {
+    file: File,
+}
+
+impl InterventionLog {
+    pub fn create(seed: u64) -> Self {
+        let Ok(mut file) = File::create(INTERVENTIONS_LOG_FILE_NAME)
+        else {
+            eprintln!("The interventions log file can't be created.");
+            exit(1);
+        };
+
+        // The interventions only repeat the run with the same seed
+        if writeln!(file, "seed = {seed}\n").is_err() {
+            eprintln!("The interventions log file can't be written.");
+        }
+
+        Self { file }
+    }
+
+    pub fn write(&mut self, tick: u64, intervention: &Intervention) {
+        if writeln!(
+            self.file,
+            "{}",
+            Self::to_event(tick, intervention)
+        )
+        .is_err()
+        {
+            eprintln!("The interventions log file can't be written.");
+        }
+    }
+
+    /// The intervention in the format of the interventions file.
+    fn to_event(tick: u64, intervention: &Intervention) -> String {
+        let scheduled = ScheduledIntervention {
+            tick,
+            intervention: intervention.clone(),
+        };
+
+        format!("[[events]]\n{}", to_string(&scheduled).unwrap())
+    }
+}
+
+/// The interventions to be triggered at specific ticks.
+pub struct Schedule {
+    /// The seed from the file, which the config's one gives way to.
+    pub seed: Option<u64>,
+    events:   VecDeque<ScheduledIntervention>,
+}
+
+impl Schedule {
+    /// Load the schedule from `INTERVENTIONS_FILE_NAME`. The file is
+    /// optional, so the schedule is empty if it doesn't exist.
+    pub fn load() -> Self {
+        let (seed, mut events) =
+            match read_to_string(INTERVENTIONS_FILE_NAME) {
+                Ok(contents) => {
+                    match from_str::<ScheduleData>(&contents) {
+                        Ok(data) => (data.seed, data.events),
+                        Err(_) => {
+                            eprintln!(
+                                "The interventions file style isn't \
+                                 correct."
+                            );
+                            exit(1);
+                        }
+                    }
+                }
+                Err(_) => (None, Vec::new()),
+            };
+
+        if events.iter().any(|event| {
+            event
+                .intervention
+                .get_radius()
+                .is_some_and(|radius| radius < 0.0)
+        }) {
+            eprintln!("The intervention radii can't be negative.");
+            exit(1);
+        }
+
+        for event in &events {
+            if let Intervention::SetConstant {
+                section,
+                key,
+                value,
+            } = &event.intervention
+            {
+                if let Err(error) = set_constant(
+                    &mut UserConstants::default(),
+                    section,
+                    key,
+                    value,
+                ) {
+                    eprintln!("{error}");
+                    exit(1);
+                }
+            }
+        }
+
+        events.sort_by_key(|event| event.tick);
+
+        Self {
+            seed,
+            events: events.into(),
+        }
+    }
+
+    #[inline(always)]
+    /// Take the interventions that have to be triggered by `tick`.
+    pub fn take_due(&mut self, tick: u64) -> Vec<Intervention> {
+        let mut due = Vec::new();
+
+        while self
+            .events
+            .front()
+            .is_some_and(|event| event.tick <= tick)
+        {
+            due.push(self.events.pop_front().unwrap().intervention);
+        }
+
+        due
+    }
+}
+
+impl Intervention {
+    #[inline(always)]
+    /// The radius of the intervention if it affects a circle.
+    fn get_radius(&self) -> Option<f32> {
+        match self {
+            Intervention::Meteor { radius, .. }
+            | Intervention::PlantBloom { radius, .. }
+            | Intervention::VirusRelease { radius, .. }
+            | Intervention::PlantPatch { radius, .. } => {
+                Some(*radius)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn apply(
+        &self,
+        bodies: &mut SpatialGrid<Body>,
+        plants: &mut SpatialGrid<Plant>,
+        crosses: &mut SpatialGrid<Cross>,
+        ids: &mut IdCounter,
+        user_constants: &UserConstants,
+        rng: &mut StdRng,
+    ) {
+        match self.clone() {
+            Intervention::Meteor { x, y, radius } => {
+                let circle = Circle::new(x, y, radius);
+
+                bodies.retain_in_circle(circle, |_, _| false);
+                plants.retain_in_circle(circle, |_, _| false);
+                crosses.retain_in_circle(circle, |_, _| false);
+            }
+            Intervention::PlantBloom { x, y, radius, n } => {
+                for _ in 0..n {
+                    Plant::spawn_plant(
+                        random_pos_in_circle(
+                            Circle::new(x, y, radius),
+                            rng,
+                        ),
+                        *PlantKind::ALL.iter().choose(rng).unwrap(),
+                        plants,
+                        ids,
+                    );
+                }
+            }
+            Intervention::VirusRelease {
+                x,
+                y,
+                radius,
+                virus,
+            } => {
+                bodies.for_each_in_circle_mut(
+                    Circle::new(x, y, radius),
+                    |_, body| body.get_virus(virus, user_constants),
+                );
+            }
+            Intervention::Cull { body_type } => {
+                bodies.retain(|_, body| body.body_type != body_type);
+            }
+            Intervention::PlantPatch {
+                x,
+                y,
+                radius,
+                n,
+                plant_kind,
+            } => {
+                for _ in 0..n {
+                    Plant::spawn_plant(
+                        random_pos_in_circle(
+                            Circle::new(x, y, radius),
+                            rng,
+                        ),
+                        plant_kind,
+                        plants,
+                        ids,
+                    );
+                }
+            }
+            Intervention::PlaceBody {
+                x,
+                y,
+                eating_strategy,
+                body_type,
+                color,
+                skills,
+                viruses,
+                energy,
+                speed,
+                vision_distance,
+                division_threshold,
+            } => {
+                let mut body = Body::new(
+                    clamp_pos(Vec2::new(x, y)),
+                    None,
+                    eating_strategy,
+                    None,
+                    None,
+                    get_color(color),
+                    body_type,
+                    Some(
+                        viruses
+                            .iter()
+                            .map(|virus| (*virus, 0.0))
+                            .collect(),
+                    ),
+                    None,
+                    None,
+                    user_constants,
+                    rng,
+                );
+
+                body.skills = skills.into_iter().collect();
+                body.set_initial_properties(
+                    speed,
+                    vision_distance,
+                    user_constants,
+                );
+
+                if let Some(energy) = energy {
+                    body.energy = energy;
+                }
+
+                if let Some(division_threshold) = division_threshold {
+                    body.division_threshold = division_threshold;
+                }
+
+                bodies.insert(ids.next_id(), body);
+            }
+            Intervention::PlaceCross {
+                x,
+                y,
+                energy,
+                body_type,
+                color,
+                viruses,
+            } => {
+                crosses.insert(
+                    ids.next_id(),
+                    Cross {
+                        pos: clamp_pos(Vec2::new(x, y)),
+                        age: 0,
+                        energy,
+                        viruses: viruses
+                            .iter()
+                            .map(|virus| (*virus, 0.0))
+                            .collect(),
+                        color: get_color(color),
+                        body_type,
+                    },
+                );
+            }
+            // The constants are set before the tick by
+            // `apply_set_constants`
+            Intervention::SetConstant { .. } => (),
+        }
+    }
+
+    /// Get the intervention bound to the pressed key, targeting `pos`.
+    pub fn from_key(
+        key: InterventionKey,
+        pos: Vec2,
+        bodies: &SpatialGrid<Body>,
+        rng: &mut StdRng,
+    ) -> Option<Self> {
+        Some(match key {
+            InterventionKey::Meteor => Intervention::Meteor {
+                x:      pos.x,
+                y:      pos.y,
+                radius: INTERVENTION_RADIUS,
+            },
+            InterventionKey::PlantBloom => Intervention::PlantBloom {
+                x:      pos.x,
+                y:      pos.y,
+                radius: INTERVENTION_RADIUS,
+                n:      PLANT_BLOOM_N,
+            },
+            InterventionKey::VirusRelease => {
+                Intervention::VirusRelease {
+                    x:      pos.x,
+                    y:      pos.y,
+                    radius: INTERVENTION_RADIUS,
+                    virus:  *Virus::ALL.iter().choose(rng).unwrap(),
+                }
+            }
+            InterventionKey::Cull => {
+                // Cull the type of the body closest to the position
+                let (_, closest_body) = *bodies
+                    .k_nearest(pos, 1, Topology::Bounded)
+                    .first()?;
+
+                Intervention::Cull {
+                    body_type: closest_body.body_type,
+                }
+            }
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum InterventionKey {
+    Meteor,
+    PlantBloom,
+    VirusRelease,
+    Cull,
+}
+
+impl InterventionKey {
+    pub const ALL: [Self; 4] = [
+        Self::Meteor,
+        Self::PlantBloom,
+        Self::VirusRelease,
+        Self::Cull,
+    ];
+
+    pub fn get_key_code(&self) -> KeyCode {
+        match self {
+            InterventionKey::Meteor => KeyCode::M,
+            InterventionKey::PlantBloom => KeyCode::B,
+            InterventionKey::VirusRelease => KeyCode::V,
+            InterventionKey::Cull => KeyCode::C,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        apply_set_constants, get_changed_constants, parse_config,
+    };
+
+    #[test]
+    fn changed_constants_are_replayed() {
+        let user_constants =
+            parse_config(&read_to_string(CONFIG_FILE_NAME).unwrap())
+                .unwrap();
+
+        let mut changed = user_constants.clone();
+        changed.plant_spawn_chance *= 2.0;
+        changed.condition_lifetime.end += 1;
+        changed.show_fps = !changed.show_fps;
+
+        let mut log = "seed = 42\n\n".to_owned();
+
+        for intervention in
+            get_changed_constants(&user_constants, &changed)
+        {
+            log += &InterventionLog::to_event(7, &intervention);
+            log += "\n";
+        }
+
+        let data = from_str::<ScheduleData>(&log).unwrap();
+        assert_eq!(data.seed, Some(42));
+        assert_eq!(data.events.len(), 3);
+
+        let interventions = data
+            .events
+            .into_iter()
+            .map(|event| event.intervention)
+            .collect::<Vec<_>>();
+        let replayed =
+            apply_set_constants(&user_constants, &interventions)
+                .unwrap();
+
+        assert_eq!(
+            replayed.plant_spawn_chance,
+            changed.plant_spawn_chance
+        );
+        assert_eq!(
+            replayed.condition_lifetime,
+            changed.condition_lifetime
+        );
+        assert_eq!(replayed.show_fps, changed.show_fps);
+    }
+}
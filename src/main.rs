@@ -2,61 +2,97 @@
 #![feature(core_intrinsics)]
 #![feature(more_float_constants)]
 #![feature(variant_count)]
-#![feature(let_chains)]
 
 mod body;
 mod cells;
+mod charts;
+mod coloring;
 mod condition;
 mod constants;
 mod cross;
+mod editor;
+mod filter;
+mod heatmap;
+mod histograms;
+mod inspector;
+mod intervention;
+mod minimap;
+mod palette;
 mod plant;
+mod render;
+mod scenario;
+mod scheduler;
+mod settings;
 mod smart_drawing;
 mod user_constants;
 mod utils;
+mod world;
 mod zoom;
 
 use body::*;
 use cells::*;
+use charts::*;
+use coloring::*;
 use condition::*;
 use constants::*;
 use cross::*;
+use editor::*;
+use filter::*;
+use heatmap::*;
+use histograms::*;
+use inspector::*;
+use intervention::*;
+use minimap::*;
+use palette::*;
 use plant::*;
+use render::*;
+use scenario::*;
+use scheduler::*;
+use settings::*;
 use smart_drawing::*;
 use user_constants::*;
 use utils::*;
+use world::*;
 use zoom::*;
 
+use macroquad::miniquad::conf::Platform;
 use macroquad::prelude::{
-    draw_circle_lines, draw_line, is_key_pressed,
-    is_mouse_button_pressed, mouse_position, next_frame,
-    screen_height, screen_width, set_fullscreen, vec2, Camera2D,
-    Conf, KeyCode, MouseButton, Rect, Vec2, WHITE,
+    is_key_down, is_key_pressed, mouse_position, next_frame,
+    screen_height, screen_width, set_default_camera, set_fullscreen,
+    vec2, Camera2D, Conf, KeyCode, Rect, Vec2, WHITE,
 };
-use rand::{rngs::StdRng, seq::IteratorRandom, SeedableRng};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::{ThreadPool, ThreadPoolBuilder};
 use std::{
-    collections::HashMap,
     intrinsics::unlikely,
     mem::variant_count,
-    sync::LazyLock,
-    time::{Duration, Instant},
+    sync::{Arc, LazyLock},
+    time::Instant,
 };
 
 fn window_conf() -> Conf {
     Conf {
         window_title: "eportal".to_owned(),
         fullscreen: true,
+        platform: Platform {
+            // The frames are limited by `Scheduler` instead
+            swap_interval: Some(0),
+            ..Default::default()
+        },
         ..Default::default()
     }
 }
 
-static FPS_DURATION: LazyLock<u128> =
-    LazyLock::new(|| Duration::from_secs(1 / FPS).as_millis());
 pub static AREA_SIZE: LazyLock<Vec2> = LazyLock::new(|| {
-    vec2(
-        // OBJECT_RADIUS is equal to one pixel when unzoomed
-        screen_width() * OBJECT_RADIUS,
-        screen_height() * OBJECT_RADIUS,
-    )
+    // There's no window in the tests
+    let screen_size = if cfg!(test) {
+        vec2(DEFAULT_SCREEN_WIDTH, DEFAULT_SCREEN_HEIGHT)
+    } else {
+        vec2(screen_width(), screen_height())
+    };
+
+    // OBJECT_RADIUS is equal to one pixel when unzoomed
+    screen_size * OBJECT_RADIUS
 });
 pub static CELLS: LazyLock<Cells> = LazyLock::new(|| {
     let mut cells = Cells::default();
@@ -82,6 +118,14 @@ pub static CELLS: LazyLock<Cells> = LazyLock::new(|| {
     cells
 });
 
+/// 0 threads means one per CPU core.
+fn build_thread_pool(threads: usize) -> ThreadPool {
+    ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .unwrap()
+}
+
 #[macroquad::main(window_conf)]
 async fn main() {
     assert_eq!(Condition::ALL.len(), variant_count::<Condition>());
@@ -94,19 +138,20 @@ async fn main() {
     set_fullscreen(true);
     next_frame().await;
 
-    // Needed for randomness
-    let mut rng = StdRng::from_rng(&mut rand::thread_rng()).unwrap();
+    let user_constants = USER_CONSTANTS.read().unwrap().clone();
 
-    // Calculations
-    let area_space = AREA_SIZE.x * AREA_SIZE.y;
+    // Interventions
+    let mut schedule = Schedule::load();
 
-    let user_constants = USER_CONSTANTS.read().unwrap();
+    // The run can be repeated with the seed, and a replayed run goes
+    // with the one it's been logged with
+    let seed = schedule
+        .seed
+        .or(user_constants.seed)
+        .unwrap_or_else(|| rand::thread_rng().gen());
 
-    *PLANTS_N.write().unwrap() =
-        (user_constants.plants_density * area_space).round() as usize;
-    *PLANTS_N_FOR_ONE_STEP.write().unwrap() =
-        (user_constants.plant_spawn_chance * area_space).round()
-            as usize;
+    // Needed for randomness outside of the evolution
+    let mut rng = StdRng::seed_from_u64(seed);
 
     // Camera
     let mut camera = Camera2D::from_display_rect(Rect::new(
@@ -128,569 +173,456 @@ async fn main() {
         },
     };
 
+    // Colors of the body types
+    let mut palette = Palette::new(seed);
+
     // Evolution stuff
-    let mut condition: Option<(Condition, (Instant, Duration))> =
-        None;
-
-    let mut bodies: Vec<Vec<HashMap<BodyId, Body>>> =
-        vec![vec![HashMap::new(); CELLS.columns]; CELLS.rows];
-    let mut plants: Vec<Vec<HashMap<PlantId, Plant>>> =
-        vec![vec![HashMap::new(); CELLS.columns]; CELLS.rows];
-    let mut crosses: Vec<Vec<HashMap<CrossId, Cross>>> =
-        vec![vec![HashMap::new(); CELLS.columns]; CELLS.rows];
-
-    // Need to be handled manually to avoid extracting all out of the cells
-    let mut plants_n = 0;
-    let mut bodies_n = 0;
-
-    // Spawn the bodies
-    for i in 0..user_constants.omnivorous_n
-        + user_constants.herbivorous_n
-        + user_constants.carnivorous_n
-    {
-        Body::randomly_spawn_body(
-            &mut bodies,
-            match i {
-                _ if (0..user_constants.omnivorous_n)
-                    .contains(&i) =>
-                {
-                    EatingStrategy::Omnivorous
-                }
-                _ if (user_constants.omnivorous_n
-                    ..user_constants.omnivorous_n
-                        + user_constants.herbivorous_n)
-                    .contains(&i) =>
-                {
-                    EatingStrategy::Herbivorous
-                }
-                _ if (user_constants.omnivorous_n
-                    + user_constants.herbivorous_n
-                    ..user_constants.omnivorous_n
-                        + user_constants.herbivorous_n
-                        + user_constants.carnivorous_n)
-                    .contains(&i) =>
-                {
-                    EatingStrategy::Carnivorous
-                }
-                _ => unreachable!(),
-            },
-            i + 1,
-            &mut rng,
-        );
+    let scenario = Scenario::load(&user_constants);
+    let mut world = World::new(
+        seed,
+        scenario.as_ref(),
+        &mut palette,
+        &user_constants,
+    );
 
-        bodies_n += 1;
-    }
+    // Zoom
+    let mut zoom = generate_zoom_struct();
 
-    // Spawn the plants
-    for _ in 0..*PLANTS_N.read().unwrap() {
-        Plant::randomly_spawn_plant(&bodies, &mut plants, &mut rng);
+    // Drawing
+    let mut batch = Batch::default();
+    let mut density_map = DensityMap::new();
+    let mut minimap = Minimap::new();
+    let mut heatmap = Heatmap::new();
+    let mut coloring = Coloring::new();
+    let mut charts = Charts::new();
+    let mut histograms = Histograms::new();
+    let mut settings = Settings::new();
+    let mut editor = Editor::new();
+    let mut view_filter =
+        ViewFilter::new(user_constants.filter.clone());
 
-        plants_n += 1;
-    }
+    // Needed for the rates of the ticks and the frames
+    let mut scheduler = Scheduler::new();
 
-    // Zoom
-    let mut zoom = generate_zoom_struct();
+    // The threads the bodies decide what to do on, rebuilt when the
+    // number changes
+    let mut threads = user_constants.threads;
+    let mut thread_pool = build_thread_pool(threads);
+
+    let mut intervention_log = InterventionLog::create(seed);
 
-    // Needed for the FPS
-    let mut last_updated = Instant::now();
+    // The constants the changes written to the log are made from
+    let mut logged_constants = user_constants.clone();
 
-    let mut is_draw_prevented = false;
+    // The body that's being inspected
+    let mut selection = Selection::default();
+
+    // The interventions triggered by the keys and the editor wait for
+    // the next tick
+    let mut triggered_interventions: Vec<Intervention> = Vec::new();
 
     loop {
+        scheduler.start_frame();
+
+        // A reloaded config only applies from the next tick on
+        let user_constants = USER_CONSTANTS.read().unwrap().clone();
+
         // Handle interactions
         if unlikely(is_key_pressed(KeyCode::Escape)) {
             std::process::exit(0);
         }
 
-        if unlikely(is_key_pressed(KeyCode::Space)) {
-            is_draw_prevented = !is_draw_prevented;
-        }
+        settings.handle_input();
 
-        if unlikely(is_mouse_button_pressed(MouseButton::Left)) {
-            if zoom.zoomed {
-                default_camera(&mut camera);
-                zoom.mouse_pos = None;
-            } else {
-                zoom.rect = None;
-                zoom.extended_rect = None;
-                zoom.rect = None;
-            }
+        // The keys only change the settings while their panel is open
+        let are_keys_free = !settings.is_open();
 
-            zoom.zoomed = !zoom.zoomed
+        // Time controls
+        if unlikely(is_key_pressed(KeyCode::Space)) {
+            scheduler.toggle_pause();
         }
 
-        if zoom.zoomed && unlikely(is_key_pressed(KeyCode::Key1)) {
-            info.body_info = !info.body_info;
+        if are_keys_free && unlikely(is_key_pressed(KeyCode::Period))
+        {
+            scheduler.step(1);
         }
 
-        if unlikely(is_key_pressed(KeyCode::Key2)) {
-            info.evolution_info.show = !info.evolution_info.show;
-            info.evolution_info.last_updated = Some(Instant::now());
+        if are_keys_free && unlikely(is_key_pressed(KeyCode::Slash)) {
+            scheduler.step(scheduler.get_step_ticks());
         }
 
-        if unlikely(is_key_pressed(KeyCode::Key3)) {
-            config_setup(false);
+        if are_keys_free && unlikely(is_key_pressed(KeyCode::Comma)) {
+            scheduler.next_step_ticks();
         }
 
-        if zoom.zoomed {
-            // There's no reason to zoom in again if the mouse position hasn't been changed
-            let current_mouse_pos = Vec2::from(mouse_position());
-            match zoom.mouse_pos {
-                Some(mouse_pos) => {
-                    if mouse_pos != current_mouse_pos {
-                        zoom.mouse_pos = Some(current_mouse_pos);
-                        get_zoom_target(&mut camera, &mut zoom);
-                    }
-                }
-                None => {
-                    zoom.mouse_pos = Some(current_mouse_pos);
-                    get_zoom_target(&mut camera, &mut zoom);
-                }
-            }
+        if are_keys_free && unlikely(is_key_pressed(KeyCode::Minus)) {
+            scheduler.slow_down();
         }
 
-        let mut new_bodies: HashMap<BodyId, Body> = HashMap::new();
-
-        let mut removed_plants: HashMap<PlantId, Vec2> =
-            HashMap::new();
-        let mut removed_bodies: HashMap<BodyId, Vec2> =
-            HashMap::new();
+        if are_keys_free && unlikely(is_key_pressed(KeyCode::Equal)) {
+            scheduler.speed_up();
+        }
 
-        Condition::update_condition(&mut condition, &mut rng);
+        if are_keys_free {
+            editor.handle_input(&world.bodies);
+        }
 
-        // Remove plants
-        let n_to_remove = (plants_n as f32
-            * (USER_CONSTANTS.read().unwrap().plant_die_chance
-                + if condition.is_some_and(|(condition, _)| {
-                    condition == Condition::Drought
-                }) {
-                    (USER_CONSTANTS.read().unwrap().plant_die_chance)
-                        * DROUGHT_PLANT_DIE_CHANCE_MULTIPLIER
+        // Camera
+        if let Some(click_pos) = handle_camera_input(
+            &camera,
+            &mut zoom,
+            are_keys_free,
+            &user_constants,
+        ) {
+            // A click on the minimap jumps to where it's been clicked, a
+            // click while editing edits unless Shift is held, a click on
+            // a body selects it, and a click anywhere else deselects the
+            // body or toggles the zoom
+            if let Some(area_pos) = zoom
+                .zoomed
+                .then(|| minimap.get_area_pos(click_pos))
+                .flatten()
+            {
+                zoom.center_pos = Some(area_pos);
+                zoom.locked = true;
+                selection.follow = false;
+            } else if editor.active
+                && !is_key_down(KeyCode::LeftShift)
+                && !is_key_down(KeyCode::RightShift)
+            {
+                triggered_interventions.extend(
+                    editor.get_intervention(
+                        camera.screen_to_world(click_pos),
+                        &world.bodies,
+                        &world.crosses,
+                        &selection,
+                        &mut palette,
+                        &user_constants,
+                    ),
+                );
+            } else if !selection.select_at(
+                camera.screen_to_world(click_pos),
+                &world.bodies,
+            ) {
+                if selection.body_id.is_some() {
+                    selection = Selection::default();
                 } else {
-                    0.0
-                })) as usize;
-
-        for _ in 0..n_to_remove {
-            loop {
-                // Pick a random cell and remove a random plant from it
-                let random_row =
-                    plants.iter().choose(&mut rng).unwrap();
-                let random_column =
-                    random_row.iter().choose(&mut rng).unwrap();
-
-                if let Some((random_plant_id, random_plant)) =
-                    random_column.iter().choose(&mut rng)
-                {
-                    if !removed_plants.contains_key(random_plant_id) {
-                        removed_plants.insert(
-                            *random_plant_id,
-                            random_plant.pos,
-                        );
-
-                        plants_n -= 1;
-                        break;
-                    }
+                    toggle_zoom(
+                        &mut zoom,
+                        click_pos,
+                        &user_constants,
+                    );
                 }
             }
         }
 
-        // Spawn a plant in a random place with a specific chance
-        let n_to_add = *PLANTS_N_FOR_ONE_STEP.read().unwrap()
-            + if condition.is_some_and(|(condition, _)| {
-                condition == Condition::Rain
-            }) {
-                (*PLANTS_N_FOR_ONE_STEP.read().unwrap() as f32
-                    * RAIN_PLANTS_N_FOR_ONE_STEP_MULTIPLIER)
-                    as usize
-            } else {
-                0
-            };
-
-        for _ in 0..n_to_add {
-            Plant::randomly_spawn_plant(
-                &bodies,
-                &mut plants,
-                &mut rng,
-            );
+        if are_keys_free && unlikely(is_key_pressed(KeyCode::H)) {
+            heatmap.next_overlay();
+        }
 
-            plants_n += 1;
+        if are_keys_free {
+            view_filter
+                .handle_input(&world.bodies, &user_constants.filter);
         }
 
-        let is_draw_mode = !is_draw_prevented
-            && last_updated.elapsed().as_millis() >= *FPS_DURATION;
-
-        // Whether enough time has passed to draw a new frame
-        for row in unsafe {
-            &mut (*(&mut bodies
-                as *mut Vec<Vec<HashMap<BodyId, Body>>>))
-        } {
-            for column in row {
-                for (body_id, body) in column {
-                    if removed_bodies.contains_key(body_id) {
-                        continue;
-                    }
+        if are_keys_free && unlikely(is_key_pressed(KeyCode::G)) {
+            charts.show = !charts.show;
+        }
 
-                    body.handle_viruses();
-                    body.handle_lifespan();
+        if are_keys_free && unlikely(is_key_pressed(KeyCode::N)) {
+            histograms.show = !histograms.show;
+        }
 
-                    // Handle if dead to become a cross
-                    if body.energy
-                        < USER_CONSTANTS.read().unwrap().min_energy
-                        || body_id.elapsed().as_secs_f32()
-                            > body.lifespan
-                    {
-                        body.status = Status::Cross;
+        if are_keys_free && unlikely(is_key_pressed(KeyCode::K)) {
+            coloring.next_mode();
+        }
 
-                        removed_bodies.insert(*body_id, body.pos);
+        if are_keys_free && unlikely(is_key_pressed(KeyCode::J)) {
+            coloring.next_skill();
+        }
 
-                        continue;
-                    }
+        if are_keys_free && unlikely(is_key_pressed(KeyCode::F)) {
+            if selection.follow {
+                selection.follow = false;
+            } else if selection.follow(&world.bodies) && !zoom.zoomed
+            {
+                zoom.scale = user_constants.max_zoom;
+            }
+        }
 
-                    if body
-                        .handle_energy(body_id, &mut removed_bodies)
-                    {
-                        continue;
-                    }
+        if are_keys_free
+            && zoom.zoomed
+            && unlikely(is_key_pressed(KeyCode::Key1))
+        {
+            info.body_info = !info.body_info;
+        }
 
-                    // Escape
-                    let mut visible_bodies = HashMap::new();
-
-                    get_visible!(body, bodies, visible_bodies);
-
-                    visible_bodies.remove(body_id);
-
-                    let chasers = &mut visible_bodies;
-
-                    chasers.retain(|other_body_id, other_body| {
-                        !removed_bodies.contains_key(other_body_id)
-                            && if let Status::FollowingTarget(
-                                target_id,
-                                _,
-                                _,
-                            ) = other_body.status
-                            {
-                                &target_id == body_id
-                            } else {
-                                false
-                            }
-                    });
-
-                    if !chasers.is_empty() {
-                        if body
-                            .skills
-                            .contains(&Skill::PrioritizeFasterChasers)
-                            && chasers.iter().any(
-                                |(_, other_body)| {
-                                    other_body.speed > body.speed
-                                },
-                            )
-                        {
-                            chasers.retain(|_, other_body| {
-                                other_body.speed > body.speed
-                            })
-                        }
+        if are_keys_free && unlikely(is_key_pressed(KeyCode::Key2)) {
+            info.evolution_info.show = !info.evolution_info.show;
+            info.evolution_info.last_updated = Some(Instant::now());
+        }
 
-                        if let Some((
-                            closest_chasing_body_id,
-                            closest_chasing_body,
-                        )) =
-                            chasers.iter().min_by(|(_, a), (_, b)| {
-                                body.pos.distance(a.pos).total_cmp(
-                                    &body.pos.distance(b.pos),
-                                )
-                            })
-                        {
-                            body.status = Status::EscapingBody(
-                                **closest_chasing_body_id,
-                                closest_chasing_body.body_type,
-                            );
+        if are_keys_free && unlikely(is_key_pressed(KeyCode::Key3)) {
+            config_setup(false);
+            settings.reload();
+            view_filter
+                .reload(&USER_CONSTANTS.read().unwrap().filter);
+        }
 
-                            let distance_to_closest_chasing_body =
-                                body.pos.distance(
-                                    closest_chasing_body.pos,
-                                );
+        for intervention_key in InterventionKey::ALL {
+            if are_keys_free
+                && unlikely(is_key_pressed(
+                    intervention_key.get_key_code(),
+                ))
+            {
+                triggered_interventions.extend(
+                    Intervention::from_key(
+                        intervention_key,
+                        camera.screen_to_world(Vec2::from(
+                            mouse_position(),
+                        )),
+                        &world.bodies,
+                        &mut rng,
+                    ),
+                );
+            }
+        }
 
-                            body.last_pos.x -= (closest_chasing_body.last_pos.x
-                            - body.last_pos.x)
-                            * (body.speed
-                            / distance_to_closest_chasing_body);
-                            body.last_pos.y -= (closest_chasing_body.last_pos.y
-                            - body.last_pos.y)
-                            * (body.speed
-                            / distance_to_closest_chasing_body);
+        // The constants changed with the settings or a reloaded config
+        // apply from the next tick on, and they're logged to be replayed
+        let mut user_constants =
+            USER_CONSTANTS.read().unwrap().clone();
+
+        if !Arc::ptr_eq(&user_constants, &logged_constants) {
+            for intervention in get_changed_constants(
+                &logged_constants,
+                &user_constants,
+            ) {
+                intervention_log.write(world.tick, &intervention);
+            }
 
-                            body.wrap();
+            logged_constants = user_constants.clone();
+        }
 
-                            continue;
-                        }
-                    }
+        if user_constants.threads != threads {
+            threads = user_constants.threads;
+            thread_pool = build_thread_pool(threads);
+        }
 
-                    // Eating
-                    let food = body.find_food(
-                        body_id,
-                        unsafe {
-                            &(*(&bodies
-                                as *const Vec<
-                                    Vec<HashMap<BodyId, Body>>,
-                                >))
-                        },
-                        unsafe {
-                            &(*(&plants
-                                as *const Vec<
-                                    Vec<HashMap<PlantId, Plant>>,
-                                >))
-                        },
-                        unsafe {
-                            &(*(&crosses
-                                as *const Vec<
-                                    Vec<HashMap<CrossId, Cross>>,
-                                >))
-                        },
-                        &removed_bodies,
-                        &removed_plants,
-                    );
+        while scheduler.next_tick(&user_constants) {
+            // Interventions scheduled for this tick or triggered by the keys
+            let mut interventions = schedule.take_due(world.tick);
+            interventions.append(&mut triggered_interventions);
 
-                    if let Some(food) = food {
-                        let distance_to_food =
-                            body.pos.distance(food.pos);
-                        if distance_to_food <= body.speed {
-                            body.energy += match body.eating_strategy
-                            {
-                                EatingStrategy::Omnivorous => {
-                                    food.energy
-                                        * USER_CONSTANTS
-                                            .read()
-                                            .unwrap()
-                                            .omnivorous_food_part
-                                }
-                                EatingStrategy::Herbivorous
-                                | EatingStrategy::Carnivorous => {
-                                    food.energy
-                                }
-                            };
-
-                            body.last_pos = food.pos;
-
-                            match food.food_type {
-                                ObjectType::Body => {
-                                    body.get_viruses(
-                                        food.viruses.unwrap(),
-                                    );
-                                    removed_bodies
-                                        .insert(food.id, food.pos);
-                                }
-                                ObjectType::Cross => {
-                                    body.get_viruses(
-                                        food.viruses.unwrap(),
-                                    );
-
-                                    let Cell { i, j } = CELLS
-                                        .get_cell_by_pos(food.pos);
-                                    crosses[i][j].remove(&food.id);
-                                }
-                                ObjectType::Plant => {
-                                    removed_plants
-                                        .insert(food.id, food.pos);
-                                    plants_n -= 1;
-                                }
-                            }
-
-                            body.status = Status::Undefined;
-                        } else {
-                            body.status = Status::FollowingTarget(
-                                food.id,
-                                food.pos,
-                                food.food_type,
-                            );
-                            body.last_pos.x += (food.pos.x
-                                - body.last_pos.x)
-                                * (body.speed / distance_to_food);
-                            body.last_pos.y += (food.pos.y
-                                - body.last_pos.y)
-                                * (body.speed / distance_to_food);
-                        }
+            for intervention in &interventions {
+                intervention_log.write(world.tick, intervention);
+            }
 
-                        continue;
-                    }
+            // The constants set by the schedule apply from this tick on
+            if let Some(changed) =
+                apply_set_constants(&user_constants, &interventions)
+            {
+                update_plants_n_for_one_step(&changed);
 
-                    // Procreate
-                    if body.handle_procreation(
-                        body_id,
-                        &mut new_bodies,
-                        &mut removed_bodies,
-                        &mut rng,
-                    ) {
-                        continue;
-                    }
+                user_constants = Arc::new(changed);
+                *USER_CONSTANTS.write().unwrap() =
+                    user_constants.clone();
+                logged_constants = user_constants.clone();
 
-                    body.handle_walking_or_idle(&mut rng);
-                }
+                settings.reload();
             }
-        }
 
-        for row in &mut crosses {
-            for column in row {
-                column.retain(|_, cross| {
-                    cross.timestamp.elapsed().as_secs()
-                        <= USER_CONSTANTS
-                            .read()
-                            .unwrap()
-                            .cross_lifespan
-                })
-            }
-        }
+            world
+                .apply_interventions(&interventions, &user_constants);
+
+            let tick = world.tick;
+            let events = world.update(&user_constants, &thread_pool);
 
-        for (body_id, body_pos) in &removed_bodies {
-            let Cell { i, j } = CELLS.get_cell_by_pos(*body_pos);
-            let body = bodies[i][j].get(body_id).unwrap();
+            for pos in events.deaths {
+                heatmap.record_death(tick, pos);
+            }
 
-            if let Status::Cross = body.status {
-                crosses[i][j].insert(*body_id, Cross::new(body));
+            for (body_id, children_ids) in events.divisions {
+                if selection.body_id == Some(body_id) {
+                    selection.children = children_ids.to_vec();
+                }
             }
 
-            bodies[i][j].remove(body_id);
-            bodies_n -= 1;
+            if world.tick.is_multiple_of(CHART_SAMPLE_TICKS_N) {
+                charts.record(
+                    &world.bodies,
+                    &world.plants,
+                    &world.conditions,
+                );
+            }
         }
 
-        let mut changed: Vec<(Instant, Vec2)> = Vec::new();
+        // Drawing
+        let alpha = scheduler.get_alpha(&user_constants);
+        coloring.update_range(&world.bodies);
 
-        for row in &mut bodies {
-            for column in row {
-                for (body_id, body) in column.iter_mut() {
-                    if body.pos != body.last_pos {
-                        changed.push((*body_id, body.pos));
-                    }
+        // Keep the followed body in the center
+        if selection.follow {
+            match selection.get_body(&world.bodies) {
+                Some(body) => {
+                    zoom.center_pos = Some(body.get_drawn_pos(alpha));
+                    zoom.locked = true;
                 }
+                None => selection.follow = false,
             }
         }
 
-        for (body_id, body_pos) in &changed {
-            let Cell { i: old_i, j: old_j } =
-                CELLS.get_cell_by_pos(*body_pos);
-            let mut body =
-                bodies[old_i][old_j].get(body_id).unwrap().clone();
-
-            bodies[old_i][old_j].remove(body_id);
+        update_camera(&mut camera, &mut zoom);
 
-            body.pos = body.last_pos;
-
-            let Cell { i: new_i, j: new_j } =
-                CELLS.get_cell_by_pos(body.pos);
-            bodies[new_i][new_j].insert(*body_id, body);
+        for region in &world.conditions {
+            region.draw(&zoom);
         }
 
-        for (new_body_id, new_body) in new_bodies {
-            let Cell { i, j } = CELLS.get_cell_by_pos(new_body.pos);
-
-            bodies[i][j].insert(new_body_id, new_body);
-            bodies_n += 1;
+        for cross in world.crosses.values() {
+            cross.draw(&zoom, &mut batch);
         }
 
-        for (plant_id, plant_pos) in &removed_plants {
-            let Cell { i, j } = CELLS.get_cell_by_pos(*plant_pos);
-            plants[i][j].remove(plant_id);
+        if zoom.scale < DENSITY_MAP_MAX_ZOOM {
+            // The plants are too small to be seen one by one
+            batch.flush();
+            density_map.draw(&mut world.plants);
+        } else {
+            for plant in
+                Plant::get_plants_to_draw(&zoom, &world.plants)
+            {
+                plant.draw(&mut batch);
+            }
         }
 
-        if is_draw_mode {
-            for row in &crosses {
-                for column in row {
-                    for cross in column.values() {
-                        cross.draw(&zoom);
+        if zoom.zoomed {
+            let mut bodies_with_info = Vec::new();
+
+            for body in world.bodies.values() {
+                let Some(color) = view_filter
+                    .filter
+                    .get_color(body, coloring.get_color(body))
+                else {
+                    continue;
+                };
+                let is_shown = view_filter.filter.matches(body);
+
+                let DrawingStrategy {
+                    body: draw_body,
+                    vision_distance: draw_vision_distance,
+                    target_line: draw_target_line,
+                } = body.get_drawing_strategy(&zoom);
+
+                let drawn_pos = body.get_drawn_pos(alpha);
+
+                if info.body_info && is_shown {
+                    if draw_vision_distance {
+                        batch.circle_lines(
+                            drawn_pos,
+                            body.vision_distance,
+                            2.0,
+                            body.color,
+                        );
                     }
-                }
-            }
-
-            if zoom.zoomed {
-                for plant in Plant::get_plants_to_draw(
-                    &zoom,
-                    &plants,
-                    &removed_plants,
-                    plants_n,
-                ) {
-                    plant.draw();
-                }
 
-                for row in &bodies {
-                    for column in row {
-                        for body in column.values() {
-                            let DrawingStrategy {
-                                body: draw_body,
-                                vision_distance: draw_vision_distance,
-                                target_line: draw_target_line,
-                            } = body.get_drawing_strategy(&zoom);
-
-                            if info.body_info {
-                                if draw_vision_distance {
-                                    draw_circle_lines(
-                                        body.pos.x,
-                                        body.pos.y,
-                                        body.vision_distance,
-                                        2.0,
-                                        body.color,
-                                    );
-                                }
-
-                                if draw_target_line {
-                                    if let Status::FollowingTarget(
-                                        _,
-                                        target_pos,
-                                        _,
-                                    ) = body.status
-                                    {
-                                        draw_line(
-                                            body.pos.x,
-                                            body.pos.y,
-                                            target_pos.x,
-                                            target_pos.y,
-                                            2.0,
-                                            WHITE,
-                                        );
-                                    }
-                                }
-                            }
-
-                            if draw_body {
-                                body.draw();
-                            }
-
-                            if draw_vision_distance && info.body_info
-                            {
-                                body.draw_info();
-                            }
-                        }
-                    }
-                }
-            } else {
-                for row in &bodies {
-                    for column in row {
-                        for body in column.values() {
-                            body.draw();
+                    if draw_target_line {
+                        if let Status::FollowingTarget(
+                            _,
+                            target_pos,
+                            _,
+                        ) = body.status
+                        {
+                            batch.line(
+                                drawn_pos, target_pos, 2.0, WHITE,
+                            );
                         }
                     }
                 }
 
-                for row in &plants {
-                    for column in row {
-                        for plant in column.values() {
-                            plant.draw();
-                        }
-                    }
+                if draw_body {
+                    body.draw(color, alpha, &mut batch);
                 }
 
-                last_updated = Instant::now();
+                if draw_vision_distance && info.body_info && is_shown
+                {
+                    bodies_with_info.push(body);
+                }
             }
 
-            if info.evolution_info.show {
-                show_evolution_info(
-                    &zoom, &mut info, plants_n, bodies_n, &condition,
-                );
-            }
+            batch.flush();
 
-            if USER_CONSTANTS.read().unwrap().show_fps {
-                show_fps(&zoom);
+            // The text can't be batched, so it goes over the shapes
+            for body in bodies_with_info {
+                body.draw_info(alpha, &user_constants);
+            }
+        } else {
+            for body in world.bodies.values() {
+                if let Some(color) = view_filter
+                    .filter
+                    .get_color(body, coloring.get_color(body))
+                {
+                    body.draw(color, alpha, &mut batch);
+                }
             }
+
+            batch.flush();
+        }
+
+        heatmap.draw(&world.plants, &world.bodies, world.tick);
+
+        selection.draw_highlight(
+            &world.bodies,
+            &zoom,
+            alpha,
+            &mut batch,
+        );
+        editor.draw_brush(
+            camera.screen_to_world(Vec2::from(mouse_position())),
+            &mut batch,
+        );
+        batch.flush();
+
+        if info.evolution_info.show {
+            show_evolution_info(
+                &zoom,
+                &mut info,
+                world.plants.len(),
+                world.bodies.len(),
+                view_filter.filter.is_active().then(|| {
+                    world
+                        .bodies
+                        .values()
+                        .filter(|body| {
+                            view_filter.filter.matches(body)
+                        })
+                        .count()
+                }),
+                &world.conditions,
+            );
+        }
+
+        if user_constants.show_fps {
+            show_fps(&zoom);
+        }
+
+        show_speed(&zoom, &scheduler, world.tick, &user_constants);
+
+        // The panels are drawn on the screen instead of the area
+        set_default_camera();
+
+        selection.draw_panel(&world.bodies, &user_constants);
+
+        if zoom.zoomed {
+            minimap.draw(&world.plants, &world.bodies, &zoom);
         }
 
+        heatmap.draw_legend();
+        coloring.draw_legend();
+        view_filter.draw_panel();
+        charts.draw();
+        histograms.draw(&world.bodies);
+        settings.draw_panel();
+        editor.draw_panel();
+
         next_frame().await;
     }
 }
@@ -0,0 +1,190 @@
+use crate::{
+    constants::*, Body, Plant, SpatialGrid, Zoom, AREA_SIZE,
+};
+use macroquad::prelude::{
+    draw_rectangle, draw_rectangle_lines, draw_texture_ex, vec2,
+    vec3, Color, DrawTextureParams, FilterMode, Image, Rect,
+    Texture2D, Vec2, Vec3, BLANK, GRAY, WHITE,
+};
+
+/// The whole area drawn small in the corner of the screen while zoomed
+/// in, so that it's clear which part of the area is shown.
+pub struct Minimap {
+    columns:      usize,
+    rows:         usize,
+    /// The sum of the colors and the number of the plants within every
+    /// texel.
+    plant_texels: Vec<(Vec3, f32)>,
+    /// The same for the bodies, colored by the eating strategy.
+    body_texels:  Vec<(Vec3, f32)>,
+    image:        Image,
+    texture:      Texture2D,
+    /// Where the minimap is on the screen.
+    rect:         Rect,
+}
+
+impl Minimap {
+    pub fn new() -> Self {
+        // OBJECT_RADIUS is equal to one pixel when unzoomed
+        let screen_size = *AREA_SIZE / OBJECT_RADIUS;
+        let size = vec2(
+            MINIMAP_WIDTH,
+            MINIMAP_WIDTH * AREA_SIZE.y / AREA_SIZE.x,
+        );
+        let rect = Rect::new(
+            screen_size.x - size.x - MINIMAP_MARGIN,
+            screen_size.y - size.y - MINIMAP_MARGIN,
+            size.x,
+            size.y,
+        );
+
+        let columns = (size.x / MINIMAP_TEXEL_SIZE).ceil() as usize;
+        let rows = (size.y / MINIMAP_TEXEL_SIZE).ceil() as usize;
+
+        let image = Image::gen_image_color(
+            columns as u16,
+            rows as u16,
+            BLANK,
+        );
+        let texture = Texture2D::from_image(&image);
+        texture.set_filter(FilterMode::Nearest);
+
+        Self {
+            columns,
+            rows,
+            plant_texels: vec![(Vec3::ZERO, 0.0); columns * rows],
+            body_texels: vec![(Vec3::ZERO, 0.0); columns * rows],
+            image,
+            texture,
+            rect,
+        }
+    }
+
+    #[inline(always)]
+    fn get_texel_i(&self, pos: Vec2) -> usize {
+        let column = ((pos.x / AREA_SIZE.x * self.columns as f32)
+            as usize)
+            .min(self.columns - 1);
+        let row = ((pos.y / AREA_SIZE.y * self.rows as f32) as usize)
+            .min(self.rows - 1);
+
+        row * self.columns + column
+    }
+
+    #[inline(always)]
+    /// The position of the area under the position on the screen, `None`
+    /// if it's outside the minimap.
+    pub fn get_area_pos(&self, screen_pos: Vec2) -> Option<Vec2> {
+        self.rect.contains(screen_pos).then(|| {
+            (screen_pos - self.rect.point()) / self.rect.size()
+                * *AREA_SIZE
+        })
+    }
+
+    /// Has to be drawn with the default camera.
+    pub fn draw(
+        &mut self,
+        plants: &SpatialGrid<Plant>,
+        bodies: &SpatialGrid<Body>,
+        zoom: &Zoom,
+    ) {
+        self.plant_texels.fill((Vec3::ZERO, 0.0));
+        self.body_texels.fill((Vec3::ZERO, 0.0));
+
+        for plant in plants.values() {
+            let color = plant.kind.get_color();
+
+            let texel_i = self.get_texel_i(plant.pos);
+
+            let (colors, n) = &mut self.plant_texels[texel_i];
+            *colors += vec3(color.r, color.g, color.b);
+            *n += 1.0;
+        }
+
+        for body in bodies.values() {
+            let color = body.eating_strategy.get_color();
+
+            let texel_i = self.get_texel_i(body.pos);
+
+            let (colors, n) = &mut self.body_texels[texel_i];
+            *colors += vec3(color.r, color.g, color.b);
+            *n += 1.0;
+        }
+
+        for (
+            ((plant_colors, plants_n), (body_colors, bodies_n)),
+            pixel,
+        ) in self
+            .plant_texels
+            .iter()
+            .zip(&self.body_texels)
+            .zip(self.image.bytes.chunks_exact_mut(4))
+        {
+            // The bodies go over the plants
+            let color = if *bodies_n > 0.0 {
+                let average = *body_colors / *bodies_n;
+
+                Color::new(average.x, average.y, average.z, 1.0)
+            } else if *plants_n > 0.0 {
+                let average = *plant_colors / *plants_n;
+
+                Color::new(
+                    average.x,
+                    average.y,
+                    average.z,
+                    (*plants_n / MINIMAP_SATURATION_N).min(1.0)
+                        * MINIMAP_PLANT_ALPHA,
+                )
+            } else {
+                BLANK
+            };
+
+            let bytes: [u8; 4] = color.into();
+            pixel.copy_from_slice(&bytes);
+        }
+
+        self.texture.update(&self.image);
+
+        draw_rectangle(
+            self.rect.x,
+            self.rect.y,
+            self.rect.w,
+            self.rect.h,
+            Color::new(0.0, 0.0, 0.0, PANEL_ALPHA),
+        );
+
+        draw_texture_ex(
+            &self.texture,
+            self.rect.x,
+            self.rect.y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(self.rect.size()),
+                ..Default::default()
+            },
+        );
+
+        draw_rectangle_lines(
+            self.rect.x,
+            self.rect.y,
+            self.rect.w,
+            self.rect.h,
+            1.0,
+            GRAY,
+        );
+
+        // What the camera shows
+        if let Some(zoom_rect) = zoom.rect {
+            let scale = self.rect.size() / *AREA_SIZE;
+
+            draw_rectangle_lines(
+                self.rect.x + zoom_rect.x * scale.x,
+                self.rect.y + zoom_rect.y * scale.y,
+                zoom_rect.w * scale.x,
+                zoom_rect.h * scale.y,
+                2.0,
+                WHITE,
+            );
+        }
+    }
+}
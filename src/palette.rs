@@ -0,0 +1,164 @@
+use crate::constants::*;
+use macroquad::prelude::{vec3, Color, Vec3, GREEN, RED, YELLOW};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Convert the color to the Oklab color space where the distance
+/// between colors matches how different they look.
+#[inline(always)]
+fn to_oklab(color: Color) -> Vec3 {
+    let linear = |c: f32| {
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+
+    let (r, g, b) =
+        (linear(color.r), linear(color.g), linear(color.b));
+
+    let l =
+        (0.41222146 * r + 0.53633255 * g + 0.051445995 * b).cbrt();
+    let m = (0.2119035 * r + 0.6806995 * g + 0.10739696 * b).cbrt();
+    let s = (0.08830246 * r + 0.28171885 * g + 0.6299787 * b).cbrt();
+
+    vec3(
+        0.21045426 * l + 0.7936178 * m - 0.004072047 * s,
+        1.9779985 * l - 2.4285922 * m + 0.4505937 * s,
+        0.025904037 * l + 0.78277177 * m - 0.80867577 * s,
+    )
+}
+
+/// Allocates colors for the body types that are as different as
+/// possible from each other and from the colors of plants and viruses.
+pub struct Palette {
+    rng:       StdRng,
+    /// The colors that have been allocated or reserved in Oklab.
+    allocated: Vec<Vec3>,
+}
+
+impl Palette {
+    /// The palette always allocates the same colors in the same order
+    /// for one seed.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng:       StdRng::seed_from_u64(seed),
+            allocated: [GREEN, YELLOW, RED]
+                .into_iter()
+                .map(to_oklab)
+                .collect(),
+        }
+    }
+
+    /// Make sure the allocated colors are different from `color`.
+    pub fn reserve(&mut self, color: Color) {
+        self.allocated.push(to_oklab(color));
+    }
+
+    /// Allocate the candidate color furthest from the allocated ones.
+    /// Once the palette is exhausted, it's still the least similar
+    /// candidate, even though it may look close to an allocated color,
+    /// so allocating never gets stuck.
+    pub fn next_color(&mut self) -> Color {
+        let (color, oklab, _) = (0..PALETTE_CANDIDATES_N)
+            .map(|_| {
+                let color = Color::from_rgba(
+                    self.rng.gen_range(COLOR_MIN..=COLOR_MAX),
+                    self.rng.gen_range(COLOR_MIN..=COLOR_MAX),
+                    self.rng.gen_range(COLOR_MIN..=COLOR_MAX),
+                    255,
+                );
+                let oklab = to_oklab(color);
+                let gap = self
+                    .allocated
+                    .iter()
+                    .map(|allocated| allocated.distance(oklab))
+                    .fold(f32::INFINITY, f32::min);
+
+                (color, oklab, gap)
+            })
+            .max_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+            .unwrap();
+
+        self.allocated.push(oklab);
+
+        color
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// As many colors as there are species in the default config.
+    const COLORS_N: usize = 12;
+    /// The least Oklab distance the first `COLORS_N` colors keep.
+    const MIN_DISTANCE: f32 = 0.1;
+    const SEEDS: std::ops::Range<u64> = 0..10;
+
+    fn allocate(seed: u64, n: usize) -> Vec<Color> {
+        let mut palette = Palette::new(seed);
+
+        (0..n).map(|_| palette.next_color()).collect()
+    }
+
+    #[test]
+    fn same_for_same_seed() {
+        for seed in SEEDS {
+            assert_eq!(
+                allocate(seed, COLORS_N),
+                allocate(seed, COLORS_N)
+            );
+        }
+
+        assert_ne!(allocate(0, COLORS_N), allocate(1, COLORS_N));
+    }
+
+    #[test]
+    fn colors_far_from_each_other() {
+        for seed in SEEDS {
+            let colors = allocate(seed, COLORS_N)
+                .into_iter()
+                .map(to_oklab)
+                .collect::<Vec<_>>();
+
+            for (i, a) in colors.iter().enumerate() {
+                for b in &colors[i + 1..] {
+                    assert!(a.distance(*b) >= MIN_DISTANCE);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn colors_far_from_plants_and_viruses() {
+        for seed in SEEDS {
+            for color in allocate(seed, COLORS_N) {
+                for reserved in [GREEN, YELLOW, RED] {
+                    assert!(
+                        to_oklab(color).distance(to_oklab(reserved))
+                            >= MIN_DISTANCE
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_allocating_when_exhausted() {
+        // Far more than the colors that can be told apart
+        let colors = allocate(0, PALETTE_CANDIDATES_N * 5);
+
+        assert_eq!(colors.len(), PALETTE_CANDIDATES_N * 5);
+
+        for color in colors {
+            let [r, g, b, a]: [u8; 4] = color.into();
+
+            for channel in [r, g, b] {
+                assert!((COLOR_MIN..=COLOR_MAX).contains(&channel));
+            }
+
+            assert_eq!(a, 255);
+        }
+    }
+}
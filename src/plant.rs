@@ -1,19 +1,18 @@
 use crate::{
-    constants::*, Body, BodyId, Cell, Zoom, AREA_SIZE, CELLS,
+    constants::*, random_pos_in_circle, Batch, Body, ConditionRegion,
+    IdCounter, ObjectId, Positioned, SpatialGrid, UserConstants,
+    Zoom, AREA_SIZE,
 };
 use macroquad::{
-    color::{GREEN, YELLOW},
-    math::Vec2,
+    color::{Color, GREEN, YELLOW},
+    math::{Circle, Vec2},
     prelude::vec2,
-    shapes::{draw_triangle, draw_triangle_lines},
 };
 use rand::{prelude::IteratorRandom, rngs::StdRng, Rng};
-use std::{
-    collections::HashMap,
-    time::{Duration, Instant},
-};
+use rustc_hash::FxHashMap;
+use serde_derive::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum PlantKind {
     Grass,
     Banana,
@@ -21,6 +20,14 @@ pub enum PlantKind {
 
 impl PlantKind {
     pub const ALL: [Self; 2] = [Self::Grass, Self::Banana];
+
+    #[inline(always)]
+    pub fn get_color(&self) -> Color {
+        match self {
+            PlantKind::Grass => GREEN,
+            PlantKind::Banana => YELLOW,
+        }
+    }
 }
 
 #[derive(PartialEq, Clone)]
@@ -29,43 +36,40 @@ pub struct Plant {
     pub kind: PlantKind,
 }
 
-pub type PlantId = Instant;
+pub type PlantId = ObjectId;
 
 impl Plant {
     #[inline(always)]
-    pub fn draw(&self) {
+    pub fn draw(&self, batch: &mut Batch) {
+        let vertices = [
+            vec2(self.pos.x, self.pos.y - OBJECT_RADIUS),
+            vec2(
+                self.pos.x + OBJECT_RADIUS * COSINE_OF_30_DEGREES,
+                self.pos.y + OBJECT_RADIUS / 2.0,
+            ),
+            vec2(
+                self.pos.x - OBJECT_RADIUS * COSINE_OF_30_DEGREES,
+                self.pos.y + OBJECT_RADIUS / 2.0,
+            ),
+        ];
+        let color = self.kind.get_color();
+
         match self.kind {
             PlantKind::Grass => {
-                draw_triangle_lines(
-                    vec2(self.pos.x, self.pos.y - OBJECT_RADIUS),
-                    vec2(
-                        self.pos.x
-                            + OBJECT_RADIUS * COSINE_OF_30_DEGREES,
-                        self.pos.y + OBJECT_RADIUS / 2.0,
-                    ),
-                    vec2(
-                        self.pos.x
-                            - OBJECT_RADIUS * COSINE_OF_30_DEGREES,
-                        self.pos.y + OBJECT_RADIUS / 2.0,
-                    ),
+                batch.triangle_lines(
+                    vertices[0],
+                    vertices[1],
+                    vertices[2],
                     2.0,
-                    GREEN,
+                    color,
                 );
             }
             PlantKind::Banana => {
-                draw_triangle(
-                    vec2(self.pos.x, self.pos.y - OBJECT_RADIUS),
-                    vec2(
-                        self.pos.x
-                            + OBJECT_RADIUS * COSINE_OF_30_DEGREES,
-                        self.pos.y + OBJECT_RADIUS / 2.0,
-                    ),
-                    vec2(
-                        self.pos.x
-                            - OBJECT_RADIUS * COSINE_OF_30_DEGREES,
-                        self.pos.y + OBJECT_RADIUS / 2.0,
-                    ),
-                    YELLOW,
+                batch.triangle(
+                    vertices[0],
+                    vertices[1],
+                    vertices[2],
+                    color,
                 );
             }
         }
@@ -83,125 +87,163 @@ impl Plant {
     /// Get the plants needed to be drawn.
     pub fn get_plants_to_draw<'a>(
         zoom: &'a Zoom,
-        plants: &'a [Vec<HashMap<PlantId, Plant>>],
-        removed_plants: &'a HashMap<PlantId, Vec2>,
-        plants_n: usize,
+        plants: &'a SpatialGrid<Plant>,
     ) -> Vec<&'a Self> {
         let mut plants_to_draw = Vec::with_capacity(
-            (plants_n as f32 * AVERAGE_PLANTS_PART_DRAWN) as usize,
+            (plants.len() as f32 * AVERAGE_PLANTS_PART_DRAWN)
+                as usize,
         );
 
-        let (i_min, i_max, j_min, j_max);
-
-        if let Some(extended_rect) = zoom.extended_rect {
-            let extended_rect_center = extended_rect.center();
-
-            i_min = ((extended_rect_center.y - extended_rect.h / 2.0)
-                / CELLS.cell_height)
-                .floor() as usize;
-            i_max = ((extended_rect_center.y + extended_rect.h / 2.0)
-                / CELLS.cell_height)
-                .floor() as usize;
-            j_min = ((extended_rect_center.x - extended_rect.w / 2.0)
-                / CELLS.cell_width)
-                .floor() as usize;
-            j_max = ((extended_rect_center.x + extended_rect.w / 2.0)
-                / CELLS.cell_width)
-                .floor() as usize;
-        } else {
-            unreachable!()
-        }
+        plants_to_draw.extend(
+            plants
+                .query_rect(zoom.extended_rect.unwrap())
+                .map(|(_, plant)| plant),
+        );
 
-        for (i, row) in plants
-            .iter()
-            .enumerate()
-            .take(i_max.min(CELLS.rows - 1) + 1)
-            .skip(i_min.max(0))
-        {
-            let i_fully_within_rectangle = i != i_min && i != i_max;
+        plants_to_draw
+    }
 
-            for (j, plants) in row
-                .iter()
-                .enumerate()
-                .take(j_max.min(CELLS.columns - 1) + 1)
-                .skip(j_min.max(0))
+    #[inline(always)]
+    /// Spawn a plant to a position `get_pos` gives if it's far enough
+    /// from the borders of the area and from the rest of the plants and
+    /// bodies. Nothing is spawned if there's no room for the plant where
+    /// it's been tried.
+    fn spawn_plant_with_gaps(
+        bodies: &SpatialGrid<Body>,
+        plants: &mut SpatialGrid<Self>,
+        ids: &mut IdCounter,
+        rng: &mut StdRng,
+        mut get_pos: impl FnMut(&mut StdRng) -> Vec2,
+    ) {
+        let border_gap = OBJECT_RADIUS + MIN_GAP;
+
+        for _ in 0..PLANT_SPAWN_ATTEMPTS_N {
+            let pos = get_pos(rng);
+            let surroundings = Circle::new(
+                pos.x,
+                pos.y,
+                OBJECT_RADIUS * 2.0 + MIN_GAP,
+            );
+
+            if (border_gap..AREA_SIZE.x - border_gap).contains(&pos.x)
+                && (border_gap..AREA_SIZE.y - border_gap)
+                    .contains(&pos.y)
+                && plants.query_circle(surroundings).next().is_none()
+                && bodies.query_circle(surroundings).next().is_none()
             {
-                let j_fully_within_rectangle =
-                    j != j_min && j != j_max;
-                if i_fully_within_rectangle
-                    && j_fully_within_rectangle
-                {
-                    // The cell is fully within the rectangle
-                    for (plant_id, plant) in plants {
-                        if !removed_plants.contains_key(plant_id) {
-                            plants_to_draw.push(plant);
-                        }
-                    }
-                } else {
-                    for (plant_id, plant) in plants {
-                        if !removed_plants.contains_key(plant_id)
-                            && zoom
-                                .extended_rect
-                                .unwrap()
-                                .contains(plant.pos)
-                        {
-                            plants_to_draw.push(plant);
-                        }
-                    }
-                }
+                plants.insert(
+                    ids.next_id(),
+                    Self {
+                        pos,
+                        kind: *PlantKind::ALL
+                            .iter()
+                            .choose(rng)
+                            .unwrap(),
+                    },
+                );
+
+                return;
             }
         }
-
-        plants_to_draw
     }
 
     #[inline(always)]
-    /// Spawn a plant to a random position on the field.
+    /// Spawn a plant to a random position within the area.
     pub fn randomly_spawn_plant(
-        bodies: &[Vec<HashMap<BodyId, Body>>],
-        plants: &mut [Vec<HashMap<PlantId, Self>>],
+        bodies: &SpatialGrid<Body>,
+        plants: &mut SpatialGrid<Self>,
+        ids: &mut IdCounter,
         rng: &mut StdRng,
     ) {
-        let mut pos = Vec2::default();
+        let border_gap = OBJECT_RADIUS + MIN_GAP;
+
+        Self::spawn_plant_with_gaps(
+            bodies,
+            plants,
+            ids,
+            rng,
+            |rng| {
+                vec2(
+                    rng.gen_range(
+                        border_gap..AREA_SIZE.x - border_gap,
+                    ),
+                    rng.gen_range(
+                        border_gap..AREA_SIZE.y - border_gap,
+                    ),
+                )
+            },
+        );
+    }
 
-        let starting_point = Instant::now();
+    #[inline(always)]
+    /// Kill the plants within the drought region with a higher chance.
+    pub fn kill_plants_in_region(
+        region: &ConditionRegion,
+        plants: &SpatialGrid<Self>,
+        removed_plants: &mut FxHashMap<PlantId, Vec2>,
+        user_constants: &UserConstants,
+        rng: &mut StdRng,
+    ) {
+        let die_chance = user_constants.plant_die_chance
+            * DROUGHT_PLANT_DIE_CHANCE_MULTIPLIER;
 
-        // Make sure the position is far enough from the rest of the plants and bodies and the borders of the area
-        loop {
-            // Make sure finding a suitable position doesn't exceed a specific time limit
-            if starting_point.elapsed().as_nanos()
-                >= Duration::from_millis(PLANT_SPAWN_TIME_LIMIT)
-                    .as_nanos()
+        for (plant_id, plant) in plants.query_circle(region.circle) {
+            if !removed_plants.contains_key(plant_id)
+                && rng.gen_range(0.0..1.0) <= die_chance
             {
-                return;
+                removed_plants.insert(*plant_id, plant.pos);
             }
+        }
+    }
 
-            pos.x = rng.gen_range(0.0..AREA_SIZE.x);
-            pos.y = rng.gen_range(0.0..AREA_SIZE.y);
-
-            if (pos.x > OBJECT_RADIUS + MIN_GAP
-                || pos.x < AREA_SIZE.x - OBJECT_RADIUS - MIN_GAP)
-                || (pos.y > OBJECT_RADIUS + MIN_GAP
-                    || pos.y < AREA_SIZE.y - OBJECT_RADIUS - MIN_GAP)
-                || {
-                    let Cell { i, j } = CELLS.get_cell_by_pos(pos);
-                    bodies[i][j].values().any(|body| {
-                        body.last_pos.distance(pos)
-                            > OBJECT_RADIUS * 2.0 + MIN_GAP
-                    })
-                }
-            {
-                break;
-            }
+    #[inline(always)]
+    /// Spawn the extra plants the rain region brings.
+    pub fn spawn_plants_in_region(
+        region: &ConditionRegion,
+        bodies: &SpatialGrid<Body>,
+        plants: &mut SpatialGrid<Self>,
+        ids: &mut IdCounter,
+        user_constants: &UserConstants,
+        rng: &mut StdRng,
+    ) {
+        let expected_n = user_constants.plant_spawn_chance
+            * region.area()
+            * RAIN_PLANTS_N_FOR_ONE_STEP_MULTIPLIER;
+
+        // The fractional part is handled as a chance for one more plant
+        let n_to_add = expected_n as usize
+            + (rng.gen_range(0.0..1.0) < expected_n.fract()) as usize;
+
+        for _ in 0..n_to_add {
+            Self::spawn_plant_with_gaps(
+                bodies,
+                plants,
+                ids,
+                rng,
+                |rng| random_pos_in_circle(region.circle, rng),
+            );
         }
+    }
 
-        let Cell { i, j } = CELLS.get_cell_by_pos(pos);
-        plants[i][j].insert(
-            Instant::now(),
-            Self {
-                pos,
-                kind: *PlantKind::ALL.iter().choose(rng).unwrap(),
-            },
-        );
+    #[inline(always)]
+    /// Spawn a plant to the position if it's within the area.
+    pub fn spawn_plant(
+        pos: Vec2,
+        kind: PlantKind,
+        plants: &mut SpatialGrid<Self>,
+        ids: &mut IdCounter,
+    ) {
+        if (0.0..AREA_SIZE.x).contains(&pos.x)
+            && (0.0..AREA_SIZE.y).contains(&pos.y)
+        {
+            plants.insert(ids.next_id(), Self { pos, kind });
+        }
+    }
+}
+
+impl Positioned for Plant {
+    #[inline(always)]
+    fn pos(&self) -> Vec2 {
+        self.pos
     }
 }
@@ -0,0 +1,409 @@
+use crate::{constants::*, Plant, SpatialGrid, AREA_SIZE};
+use macroquad::{
+    models::{draw_mesh, Mesh, Vertex},
+    prelude::{
+        draw_rectangle, draw_text, draw_texture_ex, measure_text,
+        screen_height, screen_width, vec2, vec3, Color,
+        DrawTextureParams, FilterMode, Image, Texture2D, Vec2, Vec3,
+        BLANK, WHITE,
+    },
+};
+use std::{f32::consts::PI, mem::take};
+
+#[derive(Default)]
+/// The shapes of a frame collected into one mesh, so that they're drawn
+/// with a few draw calls instead of one call per shape.
+pub struct Batch {
+    vertices: Vec<Vertex>,
+    indices:  Vec<u16>,
+}
+
+impl Batch {
+    #[inline(always)]
+    /// Make room for the shape and get the index its vertices start
+    /// from.
+    fn reserve(&mut self, indices_n: usize) -> u16 {
+        if self.indices.len() + indices_n > BATCH_MAX_INDICES_N {
+            self.flush();
+        }
+
+        self.vertices.len() as u16
+    }
+
+    #[inline(always)]
+    fn push_vertex(&mut self, pos: Vec2, color: Color) {
+        self.vertices
+            .push(Vertex::new(pos.x, pos.y, 0.0, 0.0, 0.0, color));
+    }
+
+    #[inline(always)]
+    pub fn triangle(
+        &mut self,
+        a: Vec2,
+        b: Vec2,
+        c: Vec2,
+        color: Color,
+    ) {
+        let start = self.reserve(3);
+
+        for pos in [a, b, c] {
+            self.push_vertex(pos, color);
+        }
+
+        self.indices.extend([start, start + 1, start + 2]);
+    }
+
+    #[inline(always)]
+    /// The corners go around the quad.
+    pub fn quad(&mut self, corners: [Vec2; 4], color: Color) {
+        let start = self.reserve(6);
+
+        for pos in corners {
+            self.push_vertex(pos, color);
+        }
+
+        self.indices.extend([
+            start,
+            start + 1,
+            start + 2,
+            start,
+            start + 2,
+            start + 3,
+        ]);
+    }
+
+    #[inline(always)]
+    pub fn line(
+        &mut self,
+        p1: Vec2,
+        p2: Vec2,
+        thickness: f32,
+        color: Color,
+    ) {
+        let direction = (p2 - p1).normalize_or_zero();
+        let normal =
+            vec2(-direction.y, direction.x) * thickness / 2.0;
+
+        self.quad(
+            [p1 + normal, p2 + normal, p2 - normal, p1 - normal],
+            color,
+        );
+    }
+
+    #[inline(always)]
+    pub fn triangle_lines(
+        &mut self,
+        a: Vec2,
+        b: Vec2,
+        c: Vec2,
+        thickness: f32,
+        color: Color,
+    ) {
+        self.line(a, b, thickness, color);
+        self.line(b, c, thickness, color);
+        self.line(c, a, thickness, color);
+    }
+
+    #[inline(always)]
+    pub fn circle(&mut self, center: Vec2, r: f32, color: Color) {
+        let start = self.reserve(CIRCLE_SIDES_N * 3);
+
+        self.push_vertex(center, color);
+
+        for i in 0..CIRCLE_SIDES_N {
+            self.push_vertex(
+                center + Self::circle_point(i, r),
+                color,
+            );
+        }
+
+        for i in 0..CIRCLE_SIDES_N as u16 {
+            self.indices.extend([
+                start,
+                start + 1 + i,
+                start + 1 + (i + 1) % CIRCLE_SIDES_N as u16,
+            ]);
+        }
+    }
+
+    #[inline(always)]
+    pub fn circle_lines(
+        &mut self,
+        center: Vec2,
+        r: f32,
+        thickness: f32,
+        color: Color,
+    ) {
+        for i in 0..CIRCLE_SIDES_N {
+            self.line(
+                center + Self::circle_point(i, r),
+                center + Self::circle_point(i + 1, r),
+                thickness,
+                color,
+            );
+        }
+    }
+
+    #[inline(always)]
+    /// The `i`-th vertex of a circle made of `CIRCLE_SIDES_N` sides.
+    fn circle_point(i: usize, r: f32) -> Vec2 {
+        let angle = 2.0 * PI * i as f32 / CIRCLE_SIDES_N as f32;
+
+        vec2(r * angle.cos(), r * angle.sin())
+    }
+
+    /// Draw what has been collected and start over.
+    pub fn flush(&mut self) {
+        if self.indices.is_empty() {
+            return;
+        }
+
+        let mut mesh = Mesh {
+            vertices: take(&mut self.vertices),
+            indices:  take(&mut self.indices),
+            texture:  None,
+        };
+
+        draw_mesh(&mesh);
+
+        // Keep the allocations for the next shapes
+        mesh.vertices.clear();
+        mesh.indices.clear();
+        self.vertices = mesh.vertices;
+        self.indices = mesh.indices;
+    }
+}
+
+/// The plants drawn as one texture when the whole area is shown, where
+/// every texel is colored by the plants within it.
+pub struct DensityMap {
+    columns: usize,
+    rows:    usize,
+    /// The sum of the colors and the number of the plants within every
+    /// texel.
+    texels:  Vec<(Vec3, f32)>,
+    image:   Image,
+    texture: Texture2D,
+}
+
+impl DensityMap {
+    pub fn new() -> Self {
+        let columns =
+            (AREA_SIZE.x / DENSITY_MAP_TEXEL_SIZE).ceil() as usize;
+        let rows =
+            (AREA_SIZE.y / DENSITY_MAP_TEXEL_SIZE).ceil() as usize;
+
+        let image = Image::gen_image_color(
+            columns as u16,
+            rows as u16,
+            BLANK,
+        );
+        let texture = Texture2D::from_image(&image);
+        texture.set_filter(FilterMode::Nearest);
+
+        Self {
+            columns,
+            rows,
+            texels: vec![(Vec3::ZERO, 0.0); columns * rows],
+            image,
+            texture,
+        }
+    }
+
+    #[inline(always)]
+    /// Add the plant to its texel, or take it away if `sign` is -1.
+    fn add(&mut self, plant: &Plant, sign: f32) -> usize {
+        let column = ((plant.pos.x / DENSITY_MAP_TEXEL_SIZE)
+            as usize)
+            .min(self.columns - 1);
+        let row = ((plant.pos.y / DENSITY_MAP_TEXEL_SIZE) as usize)
+            .min(self.rows - 1);
+        let index = row * self.columns + column;
+        let color = plant.kind.get_color();
+
+        let (colors, n) = &mut self.texels[index];
+        *colors += sign * vec3(color.r, color.g, color.b);
+        *n += sign;
+
+        index
+    }
+
+    #[inline(always)]
+    /// Color the pixel of the texel by the plants within it.
+    fn paint(&mut self, index: usize) {
+        let (colors, n) = self.texels[index];
+
+        let color = if n <= 0.0 {
+            BLANK
+        } else {
+            let average = colors / n;
+
+            Color::new(
+                average.x,
+                average.y,
+                average.z,
+                (n / DENSITY_MAP_SATURATION_N).min(1.0),
+            )
+        };
+
+        let bytes: [u8; 4] = color.into();
+        self.image.bytes[index * 4..index * 4 + 4]
+            .copy_from_slice(&bytes);
+    }
+
+    /// Bring the texture up to date with the plants. Only the texels of
+    /// the plants that have been added or removed are updated, unless
+    /// the changes haven't been tracked.
+    fn update(&mut self, plants: &mut SpatialGrid<Plant>) {
+        let are_changes_tracked = match plants.drain_changes() {
+            Some(changes) => {
+                // The plants only change on the ticks, and not every
+                // frame has one
+                if changes.len() == 0 {
+                    return;
+                }
+
+                for (plant, is_added) in changes {
+                    let index = self.add(
+                        &plant,
+                        if is_added { 1.0 } else { -1.0 },
+                    );
+                    self.paint(index);
+                }
+
+                true
+            }
+            None => false,
+        };
+
+        if !are_changes_tracked {
+            self.texels.fill((Vec3::ZERO, 0.0));
+
+            for plant in plants.values() {
+                self.add(plant, 1.0);
+            }
+
+            for index in 0..self.texels.len() {
+                self.paint(index);
+            }
+        }
+
+        self.texture.update(&self.image);
+    }
+
+    pub fn draw(&mut self, plants: &mut SpatialGrid<Plant>) {
+        self.update(plants);
+
+        draw_texture_ex(
+            &self.texture,
+            0.0,
+            0.0,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(
+                    vec2(self.columns as f32, self.rows as f32)
+                        * DENSITY_MAP_TEXEL_SIZE,
+                ),
+                ..Default::default()
+            },
+        );
+    }
+}
+
+#[inline(always)]
+/// The color of `t` within [0, 1] on `GRADIENT`.
+pub fn get_gradient_color(t: f32) -> Color {
+    let pos = t.clamp(0.0, 1.0) * (GRADIENT.len() - 1) as f32;
+    let i = (pos as usize).min(GRADIENT.len() - 2);
+    let part = pos - i as f32;
+
+    let (from, to) = (GRADIENT[i], GRADIENT[i + 1]);
+
+    Color::new(
+        from.r + (to.r - from.r) * part,
+        from.g + (to.g - from.g) * part,
+        from.b + (to.b - from.b) * part,
+        1.0,
+    )
+}
+
+#[inline(always)]
+fn format_legend_value(value: f32) -> String {
+    if value.abs() >= 10.0 {
+        format!("{value:.0}")
+    } else {
+        format!("{value:.2}")
+    }
+}
+
+/// Draw what the colors of the gradient mean at the bottom of the
+/// screen, with `row` legends below it. Has to be drawn with the default
+/// camera.
+pub fn draw_legend(title: &str, min: f32, max: f32, row: usize) {
+    let height = PANEL_FONT_SIZE as f32 * 2.0
+        + LEGEND_BAR_HEIGHT
+        + PANEL_LINE_GAP * 2.0
+        + PANEL_PADDING * 2.0;
+    let measured = measure_text(title, None, PANEL_FONT_SIZE, 1.0);
+    let panel_width = LEGEND_WIDTH.max(measured.width);
+
+    let x = (screen_width() - LEGEND_WIDTH) / 2.0;
+    let y =
+        screen_height() - (height + PANEL_PADDING) * (row + 1) as f32;
+
+    draw_rectangle(
+        (screen_width() - panel_width) / 2.0 - PANEL_PADDING,
+        y,
+        panel_width + PANEL_PADDING * 2.0,
+        height,
+        Color::new(0.0, 0.0, 0.0, PANEL_ALPHA),
+    );
+
+    let title_y = y + PANEL_PADDING + PANEL_FONT_SIZE as f32;
+
+    draw_text(
+        title,
+        x + (LEGEND_WIDTH - measured.width) / 2.0,
+        title_y,
+        PANEL_FONT_SIZE as f32,
+        WHITE,
+    );
+
+    let bar_y = title_y + PANEL_LINE_GAP;
+    let step_width = LEGEND_WIDTH / LEGEND_STEPS_N as f32;
+
+    for i in 0..LEGEND_STEPS_N {
+        draw_rectangle(
+            x + step_width * i as f32,
+            bar_y,
+            step_width,
+            LEGEND_BAR_HEIGHT,
+            get_gradient_color(
+                i as f32 / (LEGEND_STEPS_N - 1) as f32,
+            ),
+        );
+    }
+
+    let values_y = bar_y
+        + LEGEND_BAR_HEIGHT
+        + PANEL_LINE_GAP
+        + PANEL_FONT_SIZE as f32;
+    let max_text = format_legend_value(max);
+    let measured =
+        measure_text(&max_text, None, PANEL_FONT_SIZE, 1.0);
+
+    draw_text(
+        &format_legend_value(min),
+        x,
+        values_y,
+        PANEL_FONT_SIZE as f32,
+        WHITE,
+    );
+    draw_text(
+        &max_text,
+        x + LEGEND_WIDTH - measured.width,
+        values_y,
+        PANEL_FONT_SIZE as f32,
+        WHITE,
+    );
+}
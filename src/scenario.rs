@@ -0,0 +1,320 @@
+use crate::{
+    constants::*, random_pos_in_circle, Body, Condition,
+    ConditionRegion, Cross, EatingStrategy, IdCounter, Palette,
+    Plant, PlantKind, Skill, SpatialGrid, UserConstants, Virus,
+    AREA_SIZE,
+};
+use macroquad::prelude::{Circle, Color, Vec2};
+use rand::{rngs::StdRng, seq::IteratorRandom};
+use serde_derive::Deserialize;
+use std::{collections::HashMap, fs::read_to_string, process::exit};
+use toml::from_str;
+
+#[derive(Deserialize)]
+/// Bodies placed around a position. Every property that isn't
+/// specified is generated the way it's done for the 1st generation.
+struct ScenarioBodies {
+    x:                  f32,
+    y:                  f32,
+    /// The radius of the circle the bodies are scattered within.
+    #[serde(default)]
+    radius:             f32,
+    #[serde(default = "one")]
+    n:                  usize,
+    eating_strategy:    EatingStrategy,
+    body_type:          u32,
+    #[serde(default)]
+    skills:             Vec<Skill>,
+    #[serde(default)]
+    viruses:            Vec<Virus>,
+    /// RGB.
+    color:              Option<[u8; 3]>,
+    energy:             Option<f32>,
+    speed:              Option<f32>,
+    vision_distance:    Option<f32>,
+    division_threshold: Option<f32>,
+}
+
+#[derive(Deserialize)]
+/// Plants placed around a position.
+struct ScenarioPlants {
+    x:      f32,
+    y:      f32,
+    radius: f32,
+    n:      usize,
+    /// Both kinds are spawned if it isn't specified.
+    kind:   Option<PlantKind>,
+}
+
+#[derive(Deserialize)]
+struct ScenarioCross {
+    x:         f32,
+    y:         f32,
+    energy:    f32,
+    body_type: u32,
+    #[serde(default)]
+    viruses:   Vec<Virus>,
+    /// RGB.
+    color:     Option<[u8; 3]>,
+}
+
+#[derive(Deserialize)]
+struct ScenarioCondition {
+    condition: Condition,
+    x:         f32,
+    y:         f32,
+    radius:    f32,
+    /// In seconds.
+    lifetime:  u64,
+}
+
+#[derive(Deserialize)]
+/// The initial state of the evolution.
+pub struct Scenario {
+    /// Whether the species from `config.toml` are spawned as well.
+    #[serde(default)]
+    pub config_bodies: bool,
+    /// Whether the plants from `config.toml` are spawned as well.
+    #[serde(default = "yes")]
+    pub config_plants: bool,
+    #[serde(default)]
+    bodies:            Vec<ScenarioBodies>,
+    #[serde(default)]
+    plants:            Vec<ScenarioPlants>,
+    #[serde(default)]
+    crosses:           Vec<ScenarioCross>,
+    #[serde(default)]
+    conditions:        Vec<ScenarioCondition>,
+}
+
+fn one() -> usize {
+    1
+}
+
+fn yes() -> bool {
+    true
+}
+
+#[inline(always)]
+pub fn get_color(rgb: [u8; 3]) -> Color {
+    Color::from_rgba(rgb[0], rgb[1], rgb[2], 255)
+}
+
+#[inline(always)]
+/// Keep the position within the area.
+pub fn clamp_pos(pos: Vec2) -> Vec2 {
+    pos.clamp(Vec2::splat(MIN_GAP), *AREA_SIZE - MIN_GAP)
+}
+
+impl Scenario {
+    /// Load the scenario from `SCENARIO_FILE_NAME`. The file is
+    /// optional, so there's no scenario if it doesn't exist.
+    pub fn load(user_constants: &UserConstants) -> Option<Self> {
+        let contents = read_to_string(SCENARIO_FILE_NAME).ok()?;
+
+        let scenario: Self = match from_str(&contents) {
+            Ok(scenario) => scenario,
+            Err(_) => {
+                eprintln!("The scenario file style isn't correct.");
+                exit(1);
+            }
+        };
+
+        if let Err(error) = scenario.validate(user_constants) {
+            eprintln!("{error}");
+            exit(1);
+        }
+
+        Some(scenario)
+    }
+
+    /// Check that the radii aren't negative and that the types of the
+    /// bodies and the crosses don't collide with the types of the
+    /// species spawned along with them.
+    fn validate(
+        &self,
+        user_constants: &UserConstants,
+    ) -> Result<(), String> {
+        if self
+            .bodies
+            .iter()
+            .map(|group| group.radius)
+            .chain(self.plants.iter().map(|patch| patch.radius))
+            .chain(
+                self.conditions
+                    .iter()
+                    .map(|condition| condition.radius),
+            )
+            .any(|radius| radius < 0.0)
+        {
+            return Err(
+                "The scenario radii can't be negative.".to_owned()
+            );
+        }
+
+        if !self.config_bodies {
+            return Ok(());
+        }
+
+        let species_types = 1..=user_constants.species.len() as u32;
+
+        match self
+            .bodies
+            .iter()
+            .map(|group| group.body_type)
+            .chain(self.crosses.iter().map(|cross| cross.body_type))
+            .find(|body_type| species_types.contains(body_type))
+        {
+            Some(body_type) => Err(format!(
+                "The scenario body type {body_type} is taken by a \
+                 species, so it has to be above {}.",
+                species_types.end()
+            )),
+            None => Ok(()),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    /// Place everything the scenario describes.
+    pub fn apply(
+        &self,
+        bodies: &mut SpatialGrid<Body>,
+        plants: &mut SpatialGrid<Plant>,
+        crosses: &mut SpatialGrid<Cross>,
+        conditions: &mut Vec<ConditionRegion>,
+        palette: &mut Palette,
+        ids: &mut IdCounter,
+        user_constants: &UserConstants,
+        rng: &mut StdRng,
+    ) {
+        for rgb in
+            self.bodies.iter().filter_map(|group| group.color).chain(
+                self.crosses.iter().filter_map(|cross| cross.color),
+            )
+        {
+            palette.reserve(get_color(rgb));
+        }
+
+        // Bodies of one type share the color
+        let mut colors: HashMap<u32, Color> = HashMap::new();
+
+        for group in &self.bodies {
+            let color = match group.color {
+                Some(rgb) => get_color(rgb),
+                None => match colors.get(&group.body_type) {
+                    Some(color) => *color,
+                    None => palette.next_color(),
+                },
+            };
+
+            colors.insert(group.body_type, color);
+
+            for _ in 0..group.n {
+                let pos = clamp_pos(random_pos_in_circle(
+                    Circle::new(group.x, group.y, group.radius),
+                    rng,
+                ));
+
+                let mut body = Body::new(
+                    pos,
+                    None,
+                    group.eating_strategy,
+                    None,
+                    None,
+                    color,
+                    group.body_type,
+                    Some(
+                        group
+                            .viruses
+                            .iter()
+                            .map(|virus| (*virus, 0.0))
+                            .collect(),
+                    ),
+                    None,
+                    None,
+                    user_constants,
+                    rng,
+                );
+
+                body.skills = group.skills.iter().copied().collect();
+                body.set_initial_properties(
+                    group.speed,
+                    group.vision_distance,
+                    user_constants,
+                );
+
+                if let Some(energy) = group.energy {
+                    body.energy = energy;
+                }
+
+                if let Some(division_threshold) =
+                    group.division_threshold
+                {
+                    body.division_threshold = division_threshold;
+                }
+
+                bodies.insert(ids.next_id(), body);
+            }
+        }
+
+        for patch in &self.plants {
+            let circle = Circle::new(patch.x, patch.y, patch.radius);
+
+            for _ in 0..patch.n {
+                let kind = match patch.kind {
+                    Some(kind) => kind,
+                    None => {
+                        *PlantKind::ALL.iter().choose(rng).unwrap()
+                    }
+                };
+
+                Plant::spawn_plant(
+                    random_pos_in_circle(circle, rng),
+                    kind,
+                    plants,
+                    ids,
+                );
+            }
+        }
+
+        for cross in &self.crosses {
+            let pos = clamp_pos(Vec2::new(cross.x, cross.y));
+
+            crosses.insert(
+                ids.next_id(),
+                Cross {
+                    pos,
+                    age: 0,
+                    energy: cross.energy,
+                    viruses: cross
+                        .viruses
+                        .iter()
+                        .map(|virus| (*virus, 0.0))
+                        .collect(),
+                    color: match cross.color {
+                        Some(rgb) => get_color(rgb),
+                        None => match colors.get(&cross.body_type) {
+                            Some(color) => *color,
+                            None => palette.next_color(),
+                        },
+                    },
+                    body_type: cross.body_type,
+                },
+            );
+        }
+
+        for condition in &self.conditions {
+            conditions.push(ConditionRegion::new(
+                condition.condition,
+                Circle::new(
+                    condition.x,
+                    condition.y,
+                    condition.radius,
+                ),
+                condition.lifetime * SECOND_TICKS_N,
+                user_constants,
+                rng,
+            ));
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{constants::*, UserConstants};
+use std::{
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+/// Decides when the ticks happen, so that the evolution goes at the
+/// configured rate whatever the rate of the frames is.
+pub struct Scheduler {
+    paused:            bool,
+    /// The index of the speed within `SPEEDS`.
+    speed_i:           usize,
+    /// The index of the number of ticks within `STEP_TICKS_NS` that
+    /// are stepped through at once.
+    step_ticks_i:      usize,
+    /// The ticks left to be stepped through while paused.
+    steps_left:        u64,
+    /// The time that has passed and hasn't been simulated yet.
+    accumulator:       Duration,
+    last_accumulated:  Instant,
+    frame_start:       Instant,
+    /// Whether a tick has happened since the frame started.
+    ticked_this_frame: bool,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self {
+            paused:            false,
+            speed_i:           DEFAULT_SPEED_I,
+            step_ticks_i:      DEFAULT_STEP_TICKS_I,
+            steps_left:        0,
+            accumulator:       Duration::ZERO,
+            last_accumulated:  Instant::now(),
+            frame_start:       Instant::now(),
+            ticked_this_frame: false,
+        }
+    }
+
+    #[inline(always)]
+    /// How long a tick lasts, `None` if the ticks happen as fast as
+    /// possible.
+    fn tick_duration(
+        &self,
+        user_constants: &UserConstants,
+    ) -> Option<Duration> {
+        (user_constants.ticks_per_second > 0).then(|| {
+            Duration::from_secs_f64(
+                1.0 / (user_constants.ticks_per_second as f64
+                    * self.get_speed() as f64),
+            )
+        })
+    }
+
+    #[inline(always)]
+    /// How many times faster than `ticks_per_second` the ticks happen.
+    pub fn get_speed(&self) -> f32 {
+        SPEEDS[self.speed_i]
+    }
+
+    #[inline(always)]
+    pub fn speed_up(&mut self) {
+        self.speed_i = (self.speed_i + 1).min(SPEEDS.len() - 1);
+    }
+
+    #[inline(always)]
+    pub fn slow_down(&mut self) {
+        self.speed_i = self.speed_i.saturating_sub(1);
+    }
+
+    #[inline(always)]
+    /// How many ticks are stepped through at once.
+    pub fn get_step_ticks(&self) -> u64 {
+        STEP_TICKS_NS[self.step_ticks_i]
+    }
+
+    #[inline(always)]
+    pub fn next_step_ticks(&mut self) {
+        self.step_ticks_i =
+            (self.step_ticks_i + 1) % STEP_TICKS_NS.len();
+    }
+
+    #[inline(always)]
+    /// Pause and go `n` ticks further.
+    pub fn step(&mut self, n: u64) {
+        self.paused = true;
+        self.steps_left += n;
+    }
+
+    #[inline(always)]
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    #[inline(always)]
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.steps_left = 0;
+    }
+
+    #[inline(always)]
+    pub fn start_frame(&mut self) {
+        self.frame_start = Instant::now();
+        self.ticked_this_frame = false;
+    }
+
+    /// Whether the next tick has to happen before the frame is drawn.
+    /// The rest of the frame is waited out if there's nothing to do, so
+    /// the frames are never drawn more often than `max_fps` allows.
+    pub fn next_tick(
+        &mut self,
+        user_constants: &UserConstants,
+    ) -> bool {
+        let frame_end = self.frame_start
+            + Duration::from_secs_f64(
+                1.0 / user_constants.max_fps as f64,
+            );
+
+        loop {
+            let now = Instant::now();
+
+            if self.paused {
+                self.last_accumulated = now;
+                // The state of the last tick is drawn as it is, and the
+                // next tick happens right after resuming
+                self.accumulator = self
+                    .tick_duration(user_constants)
+                    .unwrap_or_default();
+
+                // The steps go as fast as possible, but the frames are
+                // still drawn
+                if self.steps_left > 0
+                    && (!self.ticked_this_frame || now < frame_end)
+                {
+                    self.steps_left -= 1;
+                    self.ticked_this_frame = true;
+
+                    return true;
+                }
+
+                sleep(frame_end.saturating_duration_since(now));
+
+                return false;
+            }
+
+            self.accumulator += now - self.last_accumulated;
+            self.last_accumulated = now;
+
+            // The frame is drawn even if the evolution can't keep up,
+            // but at least one tick happens in every frame
+            let can_tick = !self.ticked_this_frame || now < frame_end;
+
+            match self.tick_duration(user_constants) {
+                None => {
+                    if can_tick {
+                        self.ticked_this_frame = true;
+                    }
+
+                    return can_tick;
+                }
+                Some(tick_duration) => {
+                    // Don't try to catch up with what's been missed when
+                    // the evolution is too slow for the rate
+                    self.accumulator = self
+                        .accumulator
+                        .min(tick_duration * MAX_TICKS_BEHIND_N);
+
+                    if self.accumulator >= tick_duration && can_tick {
+                        self.accumulator -= tick_duration;
+                        self.ticked_this_frame = true;
+
+                        return true;
+                    }
+
+                    if now >= frame_end {
+                        return false;
+                    }
+
+                    sleep(
+                        (tick_duration - self.accumulator)
+                            .min(frame_end - now),
+                    );
+                }
+            }
+        }
+    }
+
+    #[inline(always)]
+    /// How far the evolution is between the last tick and the next one,
+    /// within [0, 1].
+    pub fn get_alpha(&self, user_constants: &UserConstants) -> f32 {
+        match self.tick_duration(user_constants) {
+            Some(tick_duration) => (self.accumulator.as_secs_f32()
+                / tick_duration.as_secs_f32())
+            .min(1.0),
+            None => 1.0,
+        }
+    }
+}
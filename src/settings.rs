@@ -0,0 +1,620 @@
+use crate::{
+    constants::*, draw_text_panel, get_text_panel_size,
+    update_plants_n_for_one_step, Intervention, UserConstants,
+    USER_CONSTANTS,
+};
+use macroquad::prelude::{
+    get_char_pressed, is_key_down, is_key_pressed, screen_width,
+    KeyCode, Vec2,
+};
+use std::{
+    fs::{read_to_string, write},
+    process::exit,
+    sync::Arc,
+};
+use toml::{Table, Value};
+
+enum SettingValue {
+    Number {
+        get:     fn(&UserConstants) -> f64,
+        set:     fn(&mut UserConstants, f64),
+        integer: bool,
+    },
+    Flag {
+        get: fn(&UserConstants) -> bool,
+        set: fn(&mut UserConstants, bool),
+    },
+}
+
+/// A value from the config that can be changed while the evolution is
+/// going on.
+struct Setting {
+    section:   &'static str,
+    /// The key within the section of the config.
+    key:       &'static str,
+    /// The key, told apart for the ends of the ranges.
+    label:     &'static str,
+    value:     SettingValue,
+    /// The value written to the config for the key.
+    to_toml:   fn(&UserConstants) -> String,
+    /// Set the value the way it's written to the config, `None` if it
+    /// doesn't fit.
+    from_toml: fn(&mut UserConstants, &Value) -> Option<()>,
+}
+
+#[inline(always)]
+fn get_number(value: &Value) -> Option<f64> {
+    value
+        .as_float()
+        .or_else(|| value.as_integer().map(|integer| integer as f64))
+}
+
+macro_rules! float {
+    ($section:literal, $key:ident) => {
+        Setting {
+            section:   $section,
+            key:       stringify!($key),
+            label:     stringify!($key),
+            value:     SettingValue::Number {
+                get:     |user_constants| user_constants.$key as f64,
+                set:     |user_constants, value| {
+                    user_constants.$key = value as f32
+                },
+                integer: false,
+            },
+            to_toml:   |user_constants| {
+                format!("{:?}", user_constants.$key)
+            },
+            from_toml: |user_constants, value| {
+                user_constants.$key = get_number(value)? as f32;
+                Some(())
+            },
+        }
+    };
+}
+
+macro_rules! integer {
+    ($section:literal, $key:ident) => {
+        Setting {
+            section:   $section,
+            key:       stringify!($key),
+            label:     stringify!($key),
+            value:     SettingValue::Number {
+                get:     |user_constants| user_constants.$key as f64,
+                set:     |user_constants, value| {
+                    user_constants.$key = value as _
+                },
+                integer: true,
+            },
+            to_toml:   |user_constants| {
+                user_constants.$key.to_string()
+            },
+            from_toml: |user_constants, value| {
+                user_constants.$key =
+                    value.as_integer()?.try_into().ok()?;
+                Some(())
+            },
+        }
+    };
+}
+
+macro_rules! flag {
+    ($section:literal, $key:ident) => {
+        Setting {
+            section:   $section,
+            key:       stringify!($key),
+            label:     stringify!($key),
+            value:     SettingValue::Flag {
+                get: |user_constants| user_constants.$key,
+                set: |user_constants, value| {
+                    user_constants.$key = value
+                },
+            },
+            to_toml:   |user_constants| {
+                user_constants.$key.to_string()
+            },
+            from_toml: |user_constants, value| {
+                user_constants.$key = value.as_bool()?;
+                Some(())
+            },
+        }
+    };
+}
+
+/// One end of a range, `$end` being `start` or `end`.
+macro_rules! range_end {
+    ($section:literal, $key:ident, $end:ident, $integer:literal) => {
+        Setting {
+            section:   $section,
+            key:       stringify!($key),
+            label:     concat!(
+                stringify!($key),
+                " (",
+                stringify!($end),
+                ")"
+            ),
+            value:     SettingValue::Number {
+                get:     |user_constants| {
+                    user_constants.$key.$end as f64
+                },
+                set:     |user_constants, value| {
+                    user_constants.$key.$end = value as _
+                },
+                integer: $integer,
+            },
+            to_toml:   |user_constants| {
+                format!(
+                    "[{:?}, {:?}]",
+                    user_constants.$key.start,
+                    user_constants.$key.end
+                )
+            },
+            from_toml: |user_constants, value| {
+                let [start, end] = value.as_array()?.as_slice()
+                else {
+                    return None;
+                };
+
+                user_constants.$key =
+                    get_number(start)? as _..get_number(end)? as _;
+                Some(())
+            },
+        }
+    };
+}
+
+const SETTINGS: &[Setting] = &[
+    float!("body", average_energy_omnivorous_herbivorous),
+    float!("body", average_energy_carnivorous),
+    float!("body", average_speed),
+    float!("body", average_division_threshold_omnivorous_herbivorous),
+    float!("body", average_division_threshold_carnivorous),
+    float!("body", average_vision_distance),
+    float!("body", omnivorous_food_part),
+    float!("body", carnivorous_energy_const),
+    float!("body", skills_change_chance),
+    float!("body", deviation),
+    float!("body", lifespan),
+    float!("body", min_energy),
+    integer!("body", cross_lifespan),
+    float!("body", const_for_lifespan),
+    float!("plants", plants_density),
+    float!("plants", plant_spawn_chance),
+    float!("plants", plant_die_chance),
+    float!("energy", energy_spent_const_for_mass),
+    float!("energy", energy_spent_const_for_skills),
+    float!("energy", energy_spent_const_for_vision_distance),
+    float!("energy", energy_spent_const_for_movement),
+    float!("viruses", speedvirus_first_generation_infection_chance),
+    float!("viruses", speedvirus_speed_decrease),
+    float!("viruses", speedvirus_energy_spent_for_healing),
+    float!("viruses", speedvirus_heal_energy),
+    float!("viruses", visionvirus_first_generation_infection_chance),
+    float!("viruses", visionvirus_vision_distance_decrease),
+    float!("viruses", visionvirus_energy_spent_for_healing),
+    float!("viruses", visionvirus_heal_energy),
+    float!("condition", condition_chance),
+    range_end!("condition", condition_lifetime, start, true),
+    range_end!("condition", condition_lifetime, end, true),
+    range_end!("condition", condition_radius, start, false),
+    range_end!("condition", condition_radius, end, false),
+    float!("condition", condition_drift_speed),
+    integer!("condition", max_condition_regions),
+    integer!("ui", body_info_font_size),
+    float!("ui", max_zoom),
+    flag!("ui", show_fps),
+    flag!("ui", show_energy),
+    flag!("ui", show_division_threshold),
+    flag!("ui", show_body_type),
+    flag!("ui", show_lifespan),
+    flag!("ui", show_skills),
+    flag!("ui", show_viruses),
+    integer!("performance", threads),
+    integer!("performance", ticks_per_second),
+    integer!("performance", max_fps),
+];
+
+/// Set the value of the setting the way it's written to the config.
+pub fn set_constant(
+    user_constants: &mut UserConstants,
+    section: &str,
+    key: &str,
+    value: &Value,
+) -> Result<(), String> {
+    let setting = SETTINGS
+        .iter()
+        .find(|setting| {
+            setting.section == section && setting.key == key
+        })
+        .ok_or_else(|| {
+            format!("`{key}` isn't a setting of `{section}`.")
+        })?;
+
+    (setting.from_toml)(user_constants, value)
+        .ok_or_else(|| format!("`{key}` can't be {value}."))
+}
+
+/// The settings `user_constants` has changed from `previous` as the
+/// interventions that set them, so that the changes can be replayed.
+pub fn get_changed_constants(
+    previous: &UserConstants,
+    user_constants: &UserConstants,
+) -> Vec<Intervention> {
+    let mut changed: Vec<&Setting> = Vec::new();
+
+    for setting in SETTINGS {
+        // Both ends of a range are set at once
+        if (setting.to_toml)(previous)
+            != (setting.to_toml)(user_constants)
+            && !changed.iter().any(|changed_setting| {
+                changed_setting.section == setting.section
+                    && changed_setting.key == setting.key
+            })
+        {
+            changed.push(setting);
+        }
+    }
+
+    changed
+        .into_iter()
+        .filter_map(|setting| {
+            let mut table = format!(
+                "value = {}",
+                (setting.to_toml)(user_constants)
+            )
+            .parse::<Table>()
+            .ok()?;
+
+            Some(Intervention::SetConstant {
+                section: setting.section.to_owned(),
+                key:     setting.key.to_owned(),
+                value:   table.remove("value")?,
+            })
+        })
+        .collect()
+}
+
+/// Get the constants the interventions setting them make out of
+/// `user_constants`, `None` if there are no such interventions.
+pub fn apply_set_constants(
+    user_constants: &UserConstants,
+    interventions: &[Intervention],
+) -> Option<UserConstants> {
+    let mut changed = None;
+
+    for intervention in interventions {
+        if let Intervention::SetConstant {
+            section,
+            key,
+            value,
+        } = intervention
+        {
+            let changed =
+                changed.get_or_insert_with(|| user_constants.clone());
+
+            if let Err(error) =
+                set_constant(changed, section, key, value)
+            {
+                eprintln!("{error}");
+                exit(1);
+            }
+        }
+    }
+
+    if let Some(Err(error)) =
+        changed.as_ref().map(|changed| changed.validate())
+    {
+        eprintln!(
+            "The interventions make the config invalid: {error}"
+        );
+        exit(1);
+    }
+
+    changed
+}
+
+/// The panel the values from the config are changed with. The changes
+/// apply as soon as they're valid.
+pub struct Settings {
+    show:      bool,
+    /// The constants with the changes, including the invalid ones.
+    draft:     UserConstants,
+    /// The index of the setting within `SETTINGS`.
+    setting_i: usize,
+    /// The value being typed.
+    input:     String,
+    /// What has happened with the last change or the last save.
+    message:   Option<String>,
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Self {
+            show:      false,
+            draft:     UserConstants::default(),
+            setting_i: 0,
+            input:     String::new(),
+            message:   None,
+        }
+    }
+
+    #[inline(always)]
+    /// Whether the keys are taken by the panel.
+    pub fn is_open(&self) -> bool {
+        self.show
+    }
+
+    /// Start from what the evolution goes with now, dropping the
+    /// changes that haven't been applied.
+    pub fn reload(&mut self) {
+        self.draft = (**USER_CONSTANTS.read().unwrap()).clone();
+        self.input.clear();
+        self.message = None;
+    }
+
+    /// Open or close the panel with O, and change the settings with the
+    /// keys shown on it while it's open.
+    pub fn handle_input(&mut self) {
+        if is_key_pressed(KeyCode::O) {
+            self.show = !self.show;
+
+            if self.show {
+                self.reload();
+            }
+        }
+
+        if !self.show {
+            return;
+        }
+
+        if is_key_pressed(KeyCode::LeftBracket) {
+            self.setting_i = self
+                .setting_i
+                .checked_sub(1)
+                .unwrap_or(SETTINGS.len() - 1);
+            self.input.clear();
+        }
+
+        if is_key_pressed(KeyCode::RightBracket) {
+            self.setting_i = (self.setting_i + 1) % SETTINGS.len();
+            self.input.clear();
+        }
+
+        let mut chars = Vec::new();
+
+        while let Some(char) = get_char_pressed() {
+            chars.push(char);
+        }
+
+        // The last pressed char comes first
+        for char in chars.into_iter().rev() {
+            if char.is_ascii_digit() || char == '.' || char == '-' {
+                self.input.push(char);
+            }
+        }
+
+        if is_key_pressed(KeyCode::Backspace) {
+            self.input.pop();
+        }
+
+        let setting = &SETTINGS[self.setting_i];
+
+        match setting.value {
+            SettingValue::Number { get, set, integer } => {
+                let value = get(&self.draft);
+                let step = if integer {
+                    (value * SETTINGS_STEP).round().max(1.0)
+                } else if value == 0.0 {
+                    SETTINGS_ZERO_STEP
+                } else {
+                    value.abs() * SETTINGS_STEP
+                };
+
+                if is_key_pressed(KeyCode::Semicolon) {
+                    let value = if integer {
+                        (value - step).max(0.0)
+                    } else {
+                        value - step
+                    };
+
+                    self.change(|draft| set(draft, value));
+                }
+
+                if is_key_pressed(KeyCode::Apostrophe) {
+                    self.change(|draft| set(draft, value + step));
+                }
+
+                if is_key_pressed(KeyCode::Enter)
+                    && !self.input.is_empty()
+                {
+                    match self.input.parse::<f64>() {
+                        Ok(value)
+                            if !integer
+                                || (value >= 0.0
+                                    && value.fract() == 0.0) =>
+                        {
+                            self.change(|draft| set(draft, value));
+                        }
+                        _ => {
+                            self.message = Some(format!(
+                                "`{}` has to be {}.",
+                                setting.key,
+                                if integer {
+                                    "a non-negative integer"
+                                } else {
+                                    "a number"
+                                }
+                            ))
+                        }
+                    }
+
+                    self.input.clear();
+                }
+            }
+            SettingValue::Flag { get, set } => {
+                if is_key_pressed(KeyCode::Semicolon)
+                    || is_key_pressed(KeyCode::Apostrophe)
+                    || is_key_pressed(KeyCode::Enter)
+                {
+                    let value = !get(&self.draft);
+
+                    self.change(|draft| set(draft, value));
+                }
+            }
+        }
+
+        if (is_key_down(KeyCode::LeftControl)
+            || is_key_down(KeyCode::RightControl))
+            && is_key_pressed(KeyCode::S)
+        {
+            self.message = Some(match self.save() {
+                Ok(()) => format!("Saved to {CONFIG_FILE_NAME}."),
+                Err(error) => error,
+            });
+        }
+    }
+
+    /// Change the draft and apply it if it's valid.
+    fn change(&mut self, f: impl FnOnce(&mut UserConstants)) {
+        f(&mut self.draft);
+
+        match self.draft.validate() {
+            Ok(()) => {
+                update_plants_n_for_one_step(&self.draft);
+
+                // The tick that's going on keeps the snapshot it's taken
+                *USER_CONSTANTS.write().unwrap() =
+                    Arc::new(self.draft.clone());
+
+                self.message = None;
+            }
+            Err(error) => {
+                self.message = Some(format!("Not applied: {error}"))
+            }
+        }
+    }
+
+    /// Write the values of the draft into the config, keeping everything
+    /// else in it as it is.
+    fn save(&self) -> Result<(), String> {
+        self.draft.validate()?;
+
+        let contents =
+            read_to_string(CONFIG_FILE_NAME).map_err(|_| {
+                "The config file hasn't been found.".to_owned()
+            })?;
+
+        let mut section = "";
+        let mut lines = Vec::new();
+
+        for line in contents.lines() {
+            let trimmed = line.trim();
+
+            if trimmed.starts_with('[') {
+                section = trimmed
+                    .trim_matches(|char| char == '[' || char == ']');
+            } else if let Some((key, rest)) = line.split_once('=') {
+                if let Some(setting) =
+                    SETTINGS.iter().find(|setting| {
+                        setting.section == section
+                            && setting.key == key.trim()
+                    })
+                {
+                    let value = (setting.to_toml)(&self.draft);
+
+                    lines.push(match rest.find('#') {
+                        Some(comment_i) => format!(
+                            "{} = {} {}",
+                            key.trim_end(),
+                            value,
+                            &rest[comment_i..]
+                        ),
+                        None => {
+                            format!("{} = {}", key.trim_end(), value)
+                        }
+                    });
+
+                    continue;
+                }
+            }
+
+            lines.push(line.to_owned());
+        }
+
+        write(CONFIG_FILE_NAME, lines.join("\n") + "\n").map_err(
+            |_| "The config file couldn't be written.".to_owned(),
+        )
+    }
+
+    /// Has to be drawn with the default camera.
+    pub fn draw_panel(&self) {
+        if !self.show {
+            return;
+        }
+
+        let mut lines = vec![
+            "settings (O to close)".to_owned(),
+            "[ ] choose, ; ' change, type a value and Enter, Ctrl+S \
+             save"
+                .to_owned(),
+        ];
+
+        if let Some(message) = &self.message {
+            lines.push(message.clone());
+        }
+
+        // Only the settings around the chosen one fit
+        let first_i = self
+            .setting_i
+            .saturating_sub(SETTINGS_SHOWN_N / 2)
+            .min(SETTINGS.len().saturating_sub(SETTINGS_SHOWN_N));
+
+        for (i, setting) in SETTINGS
+            .iter()
+            .enumerate()
+            .skip(first_i)
+            .take(SETTINGS_SHOWN_N)
+        {
+            let value = match setting.value {
+                SettingValue::Number { get, integer, .. } => {
+                    if integer {
+                        format!("{}", get(&self.draft) as i64)
+                    } else {
+                        format!("{:?}", get(&self.draft) as f32)
+                    }
+                }
+                SettingValue::Flag { get, .. } => {
+                    get(&self.draft).to_string()
+                }
+            };
+
+            lines.push(if i == self.setting_i {
+                format!(
+                    "> [{}] {} = {}{}",
+                    setting.section,
+                    setting.label,
+                    value,
+                    if self.input.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" -> {}_", self.input)
+                    }
+                )
+            } else {
+                format!(
+                    "  [{}] {} = {}",
+                    setting.section, setting.label, value
+                )
+            });
+        }
+
+        draw_text_panel(
+            &lines,
+            Vec2::new(
+                (screen_width() - get_text_panel_size(&lines).x)
+                    / 2.0,
+                PANEL_TOP,
+            ),
+        );
+    }
+}
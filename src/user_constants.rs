@@ -1,20 +1,19 @@
-use crate::constants::*;
+use crate::{
+    constants::*, BodyFilter, EatingStrategy, Skill, AREA_SIZE,
+};
 use macroquad::prelude::*;
 use serde_derive::Deserialize;
 use std::{
     fs::read_to_string,
     ops::Range,
     process::exit,
-    sync::{LazyLock, RwLock},
+    sync::{Arc, LazyLock, RwLock},
 };
 use toml::from_str;
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 // Average spawn attributes
 pub struct UserConstants {
-    pub omnivorous_n:                                      usize,
-    pub herbivorous_n:                                     usize,
-    pub carnivorous_n:                                     usize,
     pub average_energy_omnivorous_herbivorous:             f32,
     pub average_energy_carnivorous:                        f32,
     pub average_speed:                                     f32,
@@ -46,7 +45,11 @@ pub struct UserConstants {
     pub visionvirus_heal_energy:                           f32,
     pub condition_chance:                                  f32,
     pub condition_lifetime:                                Range<u64>,
+    pub condition_radius:                                  Range<f32>,
+    pub condition_drift_speed:                             f32,
+    pub max_condition_regions:                             usize,
     pub body_info_font_size:                               u16,
+    pub max_zoom:                                          f32,
     pub show_fps:                                          bool,
     pub show_energy:                                       bool,
     pub show_division_threshold:                           bool,
@@ -54,16 +57,165 @@ pub struct UserConstants {
     pub show_lifespan:                                     bool,
     pub show_skills:                                       bool,
     pub show_viruses:                                      bool,
+    pub threads:                                           usize,
+    pub ticks_per_second:                                  u32,
+    pub max_fps:                                           u32,
+    pub filter:                                            BodyFilter,
+    pub species: Vec<Species>,
+    /// A random one is used if it isn't specified.
+    pub seed: Option<u64>,
 }
 
-pub static USER_CONSTANTS: LazyLock<RwLock<UserConstants>> =
+/// Every tick takes a snapshot of the constants, so a reload only
+/// applies between ticks.
+pub static USER_CONSTANTS: LazyLock<RwLock<Arc<UserConstants>>> =
     LazyLock::new(|| RwLock::new(Default::default()));
 
+impl UserConstants {
+    /// Check the values the evolution can't go on with.
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, value) in [
+            ("omnivorous_food_part", self.omnivorous_food_part),
+            ("skills_change_chance", self.skills_change_chance),
+            ("plant_die_chance", self.plant_die_chance),
+            (
+                "speedvirus_first_generation_infection_chance",
+                self.speedvirus_first_generation_infection_chance,
+            ),
+            (
+                "speedvirus_speed_decrease",
+                self.speedvirus_speed_decrease,
+            ),
+            (
+                "visionvirus_first_generation_infection_chance",
+                self.visionvirus_first_generation_infection_chance,
+            ),
+            (
+                "visionvirus_vision_distance_decrease",
+                self.visionvirus_vision_distance_decrease,
+            ),
+            ("condition_chance", self.condition_chance),
+        ] {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(format!(
+                    "`{name}` has to be within [0, 1]."
+                ));
+            }
+        }
+
+        if !(self.deviation > 0.0 && self.deviation < 1.0) {
+            return Err(
+                "`deviation` has to be within (0, 1).".to_owned()
+            );
+        }
+
+        if self.max_zoom < MIN_ZOOM {
+            return Err(format!(
+                "`max_zoom` has to be at least {MIN_ZOOM}."
+            ));
+        }
+
+        if self.max_fps == 0 {
+            return Err("`max_fps` has to be positive.".to_owned());
+        }
+
+        // The values the properties are generated around with the
+        // deviation, and the ones the heal energy is generated below
+        for (name, value) in [
+            (
+                "average_energy_omnivorous_herbivorous",
+                self.average_energy_omnivorous_herbivorous,
+            ),
+            (
+                "average_energy_carnivorous",
+                self.average_energy_carnivorous,
+            ),
+            ("average_speed", self.average_speed),
+            (
+                "average_division_threshold_omnivorous_herbivorous",
+                self.average_division_threshold_omnivorous_herbivorous,
+            ),
+            (
+                "average_division_threshold_carnivorous",
+                self.average_division_threshold_carnivorous,
+            ),
+            ("average_vision_distance", self.average_vision_distance),
+            ("lifespan", self.lifespan),
+            ("speedvirus_heal_energy", self.speedvirus_heal_energy),
+            ("visionvirus_heal_energy", self.visionvirus_heal_energy),
+        ] {
+            if !value.is_finite() || value <= 0.0 {
+                return Err(format!("`{name}` has to be positive."));
+            }
+        }
+
+        if self.condition_lifetime.is_empty() {
+            return Err("`condition_lifetime` has to be a \
+                        non-empty range."
+                .to_owned());
+        }
+
+        if self.condition_radius.is_empty()
+            || self.condition_radius.start < 0.0
+        {
+            return Err("`condition_radius` has to be a non-empty \
+                        range of non-negative values."
+                .to_owned());
+        }
+
+        for species in &self.species {
+            for (name, value) in [
+                ("average_energy", species.average_energy),
+                ("average_speed", species.average_speed),
+                (
+                    "average_division_threshold",
+                    species.average_division_threshold,
+                ),
+                (
+                    "average_vision_distance",
+                    species.average_vision_distance,
+                ),
+            ] {
+                if value.is_some_and(|value| {
+                    !value.is_finite() || value <= 0.0
+                }) {
+                    return Err(format!(
+                        "`{name}` of `{}` has to be positive.",
+                        species.name
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+/// A group of bodies of one type the evolution starts with.
+pub struct Species {
+    pub name:                       String,
+    pub n:                          usize,
+    pub eating_strategy:            EatingStrategy,
+    #[serde(default)]
+    pub skills:                     Vec<Skill>,
+    /// RGB. A random color is picked if it isn't specified.
+    pub color:                      Option<[u8; 3]>,
+    /// The averages from `[body]` are used for the ones that aren't
+    /// specified.
+    pub average_energy:             Option<f32>,
+    pub average_speed:              Option<f32>,
+    pub average_division_threshold: Option<f32>,
+    pub average_vision_distance:    Option<f32>,
+}
+
 #[derive(Deserialize)]
 struct BodyField {
-    omnivorous_n:                                      usize,
-    herbivorous_n:                                     usize,
-    carnivorous_n:                                     usize,
+    /// The numbers of bodies the older configs start with instead of
+    /// `[[species]]`.
+    omnivorous_n:                                      Option<usize>,
+    herbivorous_n:                                     Option<usize>,
+    carnivorous_n:                                     Option<usize>,
     average_energy_omnivorous_herbivorous:             f32,
     average_energy_carnivorous:                        f32,
     average_speed:                                     f32,
@@ -110,6 +262,8 @@ struct VirusesField {
 #[derive(Deserialize)]
 pub struct UIField {
     body_info_font_size:     u16,
+    #[serde(default = "default_max_zoom")]
+    max_zoom:                f32,
     show_fps:                bool,
     show_energy:             bool,
     show_division_threshold: bool,
@@ -121,18 +275,67 @@ pub struct UIField {
 
 #[derive(Deserialize)]
 pub struct ConditionField {
-    condition_chance:   f32,
-    condition_lifetime: [u64; 2],
+    condition_chance:      f32,
+    condition_lifetime:    [u64; 2],
+    #[serde(default = "default_condition_radius")]
+    condition_radius:      [f32; 2],
+    #[serde(default = "default_condition_drift_speed")]
+    condition_drift_speed: f32,
+    #[serde(default = "default_max_condition_regions")]
+    max_condition_regions: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct PerformanceField {
+    threads:          usize,
+    ticks_per_second: u32,
+    max_fps:          u32,
+}
+
+impl Default for PerformanceField {
+    fn default() -> Self {
+        Self {
+            threads:          DEFAULT_THREADS,
+            ticks_per_second: DEFAULT_TICKS_PER_SECOND,
+            max_fps:          DEFAULT_MAX_FPS,
+        }
+    }
+}
+
+// The keys the older configs don't have
+fn default_max_zoom() -> f32 {
+    DEFAULT_MAX_ZOOM
+}
+
+fn default_condition_radius() -> [f32; 2] {
+    DEFAULT_CONDITION_RADIUS
+}
+
+fn default_condition_drift_speed() -> f32 {
+    DEFAULT_CONDITION_DRIFT_SPEED
+}
+
+fn default_max_condition_regions() -> usize {
+    DEFAULT_MAX_CONDITION_REGIONS
 }
 
 #[derive(Deserialize)]
 struct Data {
-    body:      BodyField,
-    plants:    PlantField,
-    energy:    EnergyField,
-    viruses:   VirusesField,
-    condition: ConditionField,
-    ui:        UIField,
+    #[serde(default)]
+    seed:        Option<u64>,
+    body:        BodyField,
+    plants:      PlantField,
+    energy:      EnergyField,
+    viruses:     VirusesField,
+    condition:   ConditionField,
+    ui:          UIField,
+    #[serde(default)]
+    performance: PerformanceField,
+    #[serde(default)]
+    filter:      BodyFilter,
+    /// Made of the numbers from `[body]` if it isn't specified.
+    species:     Option<Vec<Species>>,
 }
 
 pub fn config_setup(first_run: bool) {
@@ -148,11 +351,12 @@ pub fn config_setup(first_run: bool) {
         }
     };
 
-    let config: Data = match from_str(&contents) {
-        Ok(config) => config,
-        Err(_) => {
+    let user_constants = match parse_config(&contents) {
+        Ok(user_constants) => user_constants,
+        Err(error) => {
+            eprintln!("{error}");
+
             if first_run {
-                eprintln!("The file style isn't correct.");
                 exit(1);
             } else {
                 return;
@@ -160,95 +364,191 @@ pub fn config_setup(first_run: bool) {
         }
     };
 
+    // The area isn't known before the window has been set up, so the
+    // world does it on the first run
+    if !first_run {
+        update_plants_n_for_one_step(&user_constants);
+    }
+
+    // The tick that's going on keeps the snapshot it's taken
+    *USER_CONSTANTS.write().unwrap() = Arc::new(user_constants);
+}
+
+/// Get how many plants are tried to be spawned for one tick out of the
+/// chance, which is per unit area.
+pub fn update_plants_n_for_one_step(user_constants: &UserConstants) {
+    *PLANTS_N_FOR_ONE_STEP.write().unwrap() =
+        (user_constants.plant_spawn_chance
+            * AREA_SIZE.x
+            * AREA_SIZE.y)
+            .round() as usize;
+}
+
+/// Get the constants from the contents of the config, or the reason
+/// they can't be used.
+pub fn parse_config(contents: &str) -> Result<UserConstants, String> {
+    let config: Data = match from_str(contents) {
+        Ok(config) => config,
+        Err(_) => {
+            return Err("The file style isn't correct.".to_owned())
+        }
+    };
+
     let body = config.body;
     let plants = config.plants;
     let energy = config.energy;
     let viruses = config.viruses;
     let condition = config.condition;
     let ui = config.ui;
+    let performance = config.performance;
+    let filter = config.filter;
+    let species = config.species.unwrap_or_else(|| {
+        [
+            (body.omnivorous_n, EatingStrategy::Omnivorous),
+            (body.herbivorous_n, EatingStrategy::Herbivorous),
+            (body.carnivorous_n, EatingStrategy::Carnivorous),
+        ]
+        .into_iter()
+        .filter_map(|(n, eating_strategy)| {
+            Some(Species {
+                name: format!("{eating_strategy:?}"),
+                n: n?,
+                eating_strategy,
+                skills: Vec::new(),
+                color: None,
+                average_energy: None,
+                average_speed: None,
+                average_division_threshold: None,
+                average_vision_distance: None,
+            })
+        })
+        .collect()
+    });
 
-    let mut user_constants = USER_CONSTANTS.write().unwrap();
-    *user_constants = UserConstants {
-        omnivorous_n:                                      body
-            .omnivorous_n,
-        herbivorous_n:                                     body
-            .herbivorous_n,
-        carnivorous_n:                                     body
-            .carnivorous_n,
-        average_energy_omnivorous_herbivorous:             body
+    let user_constants = UserConstants {
+        average_energy_omnivorous_herbivorous: body
             .average_energy_omnivorous_herbivorous,
-        average_energy_carnivorous:                        body
-            .average_energy_carnivorous,
-        average_speed:                                     body
-            .average_speed,
+        average_energy_carnivorous: body.average_energy_carnivorous,
+        average_speed: body.average_speed,
         average_division_threshold_omnivorous_herbivorous: body
             .average_division_threshold_omnivorous_herbivorous,
-        average_division_threshold_carnivorous:            body
+        average_division_threshold_carnivorous: body
             .average_division_threshold_carnivorous,
-        average_vision_distance:                           body
-            .average_vision_distance,
-        omnivorous_food_part:                              body
-            .omnivorous_food_part,
-
-        carnivorous_energy_const:                      body
-            .carnivorous_energy_const,
-        const_for_lifespan:                            body
-            .const_for_lifespan,
-        skills_change_chance:                          body
-            .skills_change_chance,
-        deviation:                                     body.deviation,
-        lifespan:                                      body.lifespan,
-        min_energy:                                    body
-            .min_energy,
-        cross_lifespan:                                body
-            .cross_lifespan,
-        plants_density:                                plants
-            .plants_density,
-        plant_spawn_chance:                            plants
-            .plant_spawn_chance,
-        plant_die_chance:                              plants
-            .plant_die_chance,
-        speedvirus_first_generation_infection_chance:  viruses
+        average_vision_distance: body.average_vision_distance,
+        omnivorous_food_part: body.omnivorous_food_part,
+
+        carnivorous_energy_const: body.carnivorous_energy_const,
+        const_for_lifespan: body.const_for_lifespan,
+        skills_change_chance: body.skills_change_chance,
+        deviation: body.deviation,
+        lifespan: body.lifespan,
+        min_energy: body.min_energy,
+        cross_lifespan: body.cross_lifespan,
+        plants_density: plants.plants_density,
+        plant_spawn_chance: plants.plant_spawn_chance,
+        plant_die_chance: plants.plant_die_chance,
+        speedvirus_first_generation_infection_chance: viruses
             .speedvirus_first_generation_infection_chance,
-        speedvirus_speed_decrease:                     viruses
-            .speedvirus_speed_decrease,
-        speedvirus_energy_spent_for_healing:           viruses
+        speedvirus_speed_decrease: viruses.speedvirus_speed_decrease,
+        speedvirus_energy_spent_for_healing: viruses
             .speedvirus_energy_spent_for_healing,
-        speedvirus_heal_energy:                        viruses
-            .speedvirus_heal_energy,
+        speedvirus_heal_energy: viruses.speedvirus_heal_energy,
         visionvirus_first_generation_infection_chance: viruses
             .visionvirus_first_generation_infection_chance,
-        visionvirus_vision_distance_decrease:          viruses
+        visionvirus_vision_distance_decrease: viruses
             .visionvirus_vision_distance_decrease,
-        visionvirus_energy_spent_for_healing:          viruses
+        visionvirus_energy_spent_for_healing: viruses
             .visionvirus_energy_spent_for_healing,
-        visionvirus_heal_energy:                       viruses
-            .visionvirus_heal_energy,
-        energy_spent_const_for_mass:                   energy
+        visionvirus_heal_energy: viruses.visionvirus_heal_energy,
+        energy_spent_const_for_mass: energy
             .energy_spent_const_for_mass,
-        energy_spent_const_for_skills:                 energy
+        energy_spent_const_for_skills: energy
             .energy_spent_const_for_skills,
-        energy_spent_const_for_vision_distance:        energy
+        energy_spent_const_for_vision_distance: energy
             .energy_spent_const_for_vision_distance,
-        energy_spent_const_for_movement:               energy
+        energy_spent_const_for_movement: energy
             .energy_spent_const_for_movement,
-        condition_chance:                              condition
-            .condition_chance,
-        condition_lifetime:                            condition
-            .condition_lifetime[0]
+        condition_chance: condition.condition_chance,
+        condition_lifetime: condition.condition_lifetime[0]
             ..condition.condition_lifetime[1],
-        body_info_font_size:                           ui
-            .body_info_font_size,
-        show_fps:                                      ui.show_fps,
-        show_energy:                                   ui.show_energy,
-        show_division_threshold:                       ui
-            .show_division_threshold,
-        show_body_type:                                ui
-            .show_body_type,
-        show_lifespan:                                 ui
-            .show_lifespan,
-        show_skills:                                   ui.show_skills,
-        show_viruses:                                  ui
-            .show_viruses,
+        condition_radius: condition.condition_radius[0]
+            ..condition.condition_radius[1],
+        condition_drift_speed: condition.condition_drift_speed,
+        max_condition_regions: condition.max_condition_regions,
+        body_info_font_size: ui.body_info_font_size,
+        max_zoom: ui.max_zoom,
+        show_fps: ui.show_fps,
+        show_energy: ui.show_energy,
+        show_division_threshold: ui.show_division_threshold,
+        show_body_type: ui.show_body_type,
+        show_lifespan: ui.show_lifespan,
+        show_skills: ui.show_skills,
+        show_viruses: ui.show_viruses,
+        threads: performance.threads,
+        ticks_per_second: performance.ticks_per_second,
+        max_fps: performance.max_fps,
+        filter,
+        species,
+        seed: config.seed,
     };
+
+    user_constants.validate()?;
+
+    Ok(user_constants)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_non_positive_averages() {
+        let user_constants =
+            parse_config(&read_to_string(CONFIG_FILE_NAME).unwrap())
+                .unwrap();
+
+        let fields: [fn(&mut UserConstants) -> &mut f32; 9] = [
+            |user_constants| {
+                &mut user_constants
+                    .average_energy_omnivorous_herbivorous
+            },
+            |user_constants| {
+                &mut user_constants.average_energy_carnivorous
+            },
+            |user_constants| &mut user_constants.average_speed,
+            |user_constants| {
+                &mut user_constants
+                    .average_division_threshold_omnivorous_herbivorous
+            },
+            |user_constants| {
+                &mut user_constants
+                    .average_division_threshold_carnivorous
+            },
+            |user_constants| {
+                &mut user_constants.average_vision_distance
+            },
+            |user_constants| &mut user_constants.lifespan,
+            |user_constants| {
+                &mut user_constants.speedvirus_heal_energy
+            },
+            |user_constants| {
+                &mut user_constants.visionvirus_heal_energy
+            },
+        ];
+
+        for field in fields {
+            for value in [0.0, -1.0] {
+                let mut changed = user_constants.clone();
+                *field(&mut changed) = value;
+
+                assert!(changed.validate().is_err());
+            }
+        }
+
+        let mut changed = user_constants.clone();
+        changed.species[0].average_speed = Some(0.0);
+
+        assert!(changed.validate().is_err());
+        assert!(user_constants.validate().is_ok());
+    }
 }
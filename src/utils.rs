@@ -1,13 +1,22 @@
 use crate::{
-    constants::*, user_constants::*, Condition, Zoom, AREA_SIZE,
+    constants::*, user_constants::*, Condition, ConditionRegion,
+    ObjectId, Scheduler, Zoom, AREA_SIZE,
 };
-use ::rand::{rngs::StdRng, Rng};
+use ::rand::{rngs::StdRng, Rng, SeedableRng};
 use macroquad::prelude::*;
-use std::time::{Duration, Instant};
+use rustc_hash::FxHasher;
+use std::{
+    f32::consts::PI,
+    hash::{Hash, Hasher},
+    time::Instant,
+};
 
 pub struct LastInfo {
-    pub plants_n: usize,
-    pub bodies_n: usize,
+    pub plants_n:       usize,
+    pub bodies_n:       usize,
+    /// The number of the bodies matching the filter, `None` if there's
+    /// no filter.
+    pub shown_bodies_n: Option<usize>,
 }
 
 pub struct EvolutionInfo {
@@ -22,16 +31,14 @@ pub struct Info {
 }
 
 pub fn generate_zoom_struct() -> Zoom {
-    let scaling_width = MAX_ZOOM / AREA_SIZE.x * 2.0;
-    let scaling_height = MAX_ZOOM / AREA_SIZE.y * 2.0;
-
     Zoom {
-        zoomed: false,
-        scaling_width,
-        scaling_height,
-        center_pos: None,
-        mouse_pos: None,
-        rect: None,
+        zoomed:        false,
+        scale:         MIN_ZOOM,
+        center_pos:    None,
+        mouse_pos:     None,
+        locked:        false,
+        drag_distance: None,
+        rect:          None,
         extended_rect: None,
     }
 }
@@ -42,10 +49,12 @@ pub fn show_evolution_info(
     info: &mut Info,
     plants_n: usize,
     bodies_n: usize,
-    condition: &Option<(Condition, (Instant, Duration))>,
+    shown_bodies_n: Option<usize>,
+    conditions: &[ConditionRegion],
 ) {
     let plants_n_to_show;
     let bodies_n_to_show;
+    let shown_bodies_n_to_show;
 
     match info.evolution_info.last_info {
         Some(_) => {
@@ -60,13 +69,16 @@ pub fn show_evolution_info(
                 let LastInfo {
                     plants_n: last_plants_n,
                     bodies_n: last_bodies_n,
+                    shown_bodies_n: last_shown_bodies_n,
                 } = info.evolution_info.last_info.as_mut().unwrap();
 
                 *last_plants_n = plants_n;
                 *last_bodies_n = bodies_n;
+                *last_shown_bodies_n = shown_bodies_n;
 
                 plants_n_to_show = plants_n;
                 bodies_n_to_show = bodies_n;
+                shown_bodies_n_to_show = shown_bodies_n;
 
                 info.evolution_info.last_updated =
                     Some(Instant::now());
@@ -74,18 +86,24 @@ pub fn show_evolution_info(
                 let LastInfo {
                     plants_n: last_plants_n,
                     bodies_n: last_bodies_n,
+                    shown_bodies_n: last_shown_bodies_n,
                 } = info.evolution_info.last_info.as_ref().unwrap();
 
                 plants_n_to_show = *last_plants_n;
                 bodies_n_to_show = *last_bodies_n;
+                shown_bodies_n_to_show = *last_shown_bodies_n;
             }
         }
         None => {
-            info.evolution_info.last_info =
-                Some(LastInfo { plants_n, bodies_n });
+            info.evolution_info.last_info = Some(LastInfo {
+                plants_n,
+                bodies_n,
+                shown_bodies_n,
+            });
 
             plants_n_to_show = plants_n;
             bodies_n_to_show = bodies_n;
+            shown_bodies_n_to_show = shown_bodies_n;
 
             info.evolution_info.last_updated = Some(Instant::now());
         }
@@ -93,16 +111,34 @@ pub fn show_evolution_info(
 
     let evolution_info_fields = [
         format!("plants: {:?}", plants_n_to_show),
-        format!("bodies: {:?}", bodies_n_to_show),
+        match shown_bodies_n_to_show {
+            Some(shown_bodies_n) => format!(
+                "bodies: {:?} ({:?} shown)",
+                bodies_n_to_show, shown_bodies_n
+            ),
+            None => format!("bodies: {:?}", bodies_n_to_show),
+        },
         format!(
-            "condition: {}",
-            match condition {
-                Some((condition, _)) => {
-                    format!("{:?}", condition)
-                }
-                None => {
-                    "Normal".to_string()
-                }
+            "conditions: {}",
+            if conditions.is_empty() {
+                "Normal".to_string()
+            } else {
+                Condition::ALL
+                    .iter()
+                    .filter_map(|condition| {
+                        let n = conditions
+                            .iter()
+                            .filter(|region| {
+                                &region.condition == condition
+                            })
+                            .count();
+
+                        (n > 0).then(|| {
+                            format!("{:?} x{}", condition, n)
+                        })
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
             }
         ),
     ];
@@ -112,7 +148,7 @@ pub fn show_evolution_info(
     if zoom.zoomed {
         for field in evolution_info_fields {
             let evolution_info_font_size =
-                (EVOLUTION_INFO_FONT_SIZE as f32 / MAX_ZOOM) as u16;
+                (EVOLUTION_INFO_FONT_SIZE as f32 / zoom.scale) as u16;
             let measured = measure_text(
                 &field,
                 None,
@@ -129,7 +165,8 @@ pub fn show_evolution_info(
                 WHITE,
             );
 
-            gap += measured.offset_y + EVOLUTION_INFO_GAP / MAX_ZOOM;
+            gap +=
+                measured.offset_y + EVOLUTION_INFO_GAP / zoom.scale;
         }
     } else {
         for field in evolution_info_fields {
@@ -161,7 +198,7 @@ pub fn show_fps(zoom: &Zoom) {
     );
 
     if zoom.zoomed {
-        let font_size = (FPS_FONT_SIZE as f32 / MAX_ZOOM) as u16;
+        let font_size = (FPS_FONT_SIZE as f32 / zoom.scale) as u16;
 
         let measured = measure_text(&text, None, font_size, 1.0);
 
@@ -185,24 +222,88 @@ pub fn show_fps(zoom: &Zoom) {
     }
 }
 
-/// Adjust the coordinates according to the borders.
 #[inline(always)]
-pub fn adjusted_pos(pos: Vec2) -> Vec2 {
-    vec2(
-        (pos.x * MAX_ZOOM)
-            .max(AREA_SIZE.x / MAX_ZOOM / 2.0)
-            .min(AREA_SIZE.x * (1.0 - 1.0 / (2.0 * MAX_ZOOM))),
-        (pos.y * MAX_ZOOM)
-            .max(AREA_SIZE.y / MAX_ZOOM / 2.0)
-            .min(AREA_SIZE.y * (1.0 - 1.0 / (2.0 * MAX_ZOOM))),
-    )
+/// Show the tick, how fast the evolution goes and how many ticks are
+/// stepped through at once.
+pub fn show_speed(
+    zoom: &Zoom,
+    scheduler: &Scheduler,
+    tick: u64,
+    user_constants: &UserConstants,
+) {
+    let speed = if scheduler.is_paused() {
+        "paused".to_owned()
+    } else if user_constants.ticks_per_second == 0 {
+        "max".to_owned()
+    } else {
+        format!("x{}", scheduler.get_speed())
+    };
+    let text = format!(
+        "tick {tick} | {speed} | step {}",
+        scheduler.get_step_ticks()
+    );
+
+    if zoom.zoomed {
+        let font_size = (SPEED_FONT_SIZE as f32 / zoom.scale) as u16;
+
+        let measured = measure_text(&text, None, font_size, 1.0);
+
+        draw_text(
+            &text,
+            zoom.rect.unwrap().x,
+            zoom.rect.unwrap().y + zoom.rect.unwrap().h
+                - (measured.height - measured.offset_y),
+            font_size as f32,
+            WHITE,
+        );
+    } else {
+        let measured =
+            measure_text(&text, None, SPEED_FONT_SIZE, 1.0);
+
+        draw_text(
+            &text,
+            0.0,
+            AREA_SIZE.y - (measured.height - measured.offset_y),
+            SPEED_FONT_SIZE as f32,
+            WHITE,
+        );
+    }
 }
 
 /// Used for getting specific values with deviations.
 #[inline(always)]
-pub fn get_with_deviation(value: f32, rng: &mut StdRng) -> f32 {
-    let user_constants = USER_CONSTANTS.read().unwrap();
-
+pub fn get_with_deviation(
+    value: f32,
+    user_constants: &UserConstants,
+    rng: &mut StdRng,
+) -> f32 {
     let part = value * user_constants.deviation;
     rng.gen_range(value - part..value + part)
 }
+
+/// Get a random position within the circle.
+#[inline(always)]
+pub fn random_pos_in_circle(
+    circle: Circle,
+    rng: &mut StdRng,
+) -> Vec2 {
+    let angle = rng.gen_range(0.0..2.0 * PI);
+    // The square root makes the positions uniformly distributed
+    let distance = circle.r * rng.gen_range(0.0f32..1.0).sqrt();
+
+    vec2(
+        circle.x + distance * angle.cos(),
+        circle.y + distance * angle.sin(),
+    )
+}
+
+#[inline(always)]
+/// Get the random number generator of the object for the tick. It only
+/// depends on `seed` and `id`, so it's the same whatever thread the
+/// object is handled on.
+pub fn get_object_rng(seed: u64, id: &ObjectId) -> StdRng {
+    let mut hasher = FxHasher::default();
+    id.hash(&mut hasher);
+
+    StdRng::seed_from_u64(seed ^ hasher.finish())
+}
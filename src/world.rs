@@ -0,0 +1,439 @@
+use crate::{
+    constants::*, get_object_rng, update_plants_n_for_one_step,
+    Action, Body, BodyId, Condition, ConditionRegion, Cross,
+    EatingStrategy, IdCounter, Intervention, ObjectType, Palette,
+    Plant, PlantId, Scenario, SpatialGrid, Status, UserConstants,
+    AREA_SIZE, AREA_TOPOLOGY, CELLS,
+};
+use macroquad::prelude::{Color, Vec2};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::{prelude::*, ThreadPool};
+use rustc_hash::FxHashMap;
+
+#[derive(Default)]
+/// What has happened during a tick that's shown outside of the world.
+pub struct TickEvents {
+    /// Where the bodies have died, the eaten ones included.
+    pub deaths:    Vec<Vec2>,
+    /// The bodies that have divided along with their children.
+    pub divisions: Vec<(BodyId, [BodyId; 2])>,
+}
+
+/// Everything the evolution consists of. It only depends on the seed,
+/// the config and the interventions, so a run can be repeated.
+pub struct World {
+    pub bodies:       SpatialGrid<Body>,
+    pub plants:       SpatialGrid<Plant>,
+    pub crosses:      SpatialGrid<Cross>,
+    pub conditions:   Vec<ConditionRegion>,
+    /// How many ticks have passed.
+    pub tick:         u64,
+    pub ids:          IdCounter,
+    seed:             u64,
+    rng:              StdRng,
+    /// What the bodies have decided during the tick. This and the rest
+    /// of the tick buffers are kept across the ticks to avoid
+    /// allocating.
+    decisions:        Vec<(BodyId, Body, Action)>,
+    new_bodies:       Vec<(BodyId, Body)>,
+    removed_plants:   FxHashMap<PlantId, Vec2>,
+    removed_bodies:   FxHashMap<BodyId, Vec2>,
+    /// The bodies that have left their cells during the tick.
+    migrating_bodies: Vec<(BodyId, Vec2)>,
+}
+
+impl World {
+    /// Place what the evolution starts with.
+    pub fn new(
+        seed: u64,
+        scenario: Option<&Scenario>,
+        palette: &mut Palette,
+        user_constants: &UserConstants,
+    ) -> Self {
+        let area_space = AREA_SIZE.x * AREA_SIZE.y;
+
+        // Has to be known before the cells are
+        *PLANTS_N.write().unwrap() = (user_constants.plants_density
+            * area_space)
+            .round() as usize;
+        update_plants_n_for_one_step(user_constants);
+
+        let mut world = Self {
+            bodies: SpatialGrid::new(AREA_TOPOLOGY),
+            plants: SpatialGrid::new(AREA_TOPOLOGY),
+            crosses: SpatialGrid::new(AREA_TOPOLOGY),
+            conditions: Vec::new(),
+            tick: 0,
+            ids: IdCounter::default(),
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            decisions: Vec::new(),
+            new_bodies: Vec::new(),
+            removed_plants: FxHashMap::default(),
+            removed_bodies: FxHashMap::default(),
+            migrating_bodies: Vec::new(),
+        };
+
+        for species in &user_constants.species {
+            if let Some([r, g, b]) = species.color {
+                palette.reserve(Color::from_rgba(r, g, b, 255));
+            }
+        }
+
+        // Place what the scenario describes
+        if let Some(scenario) = scenario {
+            scenario.apply(
+                &mut world.bodies,
+                &mut world.plants,
+                &mut world.crosses,
+                &mut world.conditions,
+                palette,
+                &mut world.ids,
+                user_constants,
+                &mut world.rng,
+            );
+        }
+
+        // Spawn the bodies
+        if scenario.is_none_or(|scenario| scenario.config_bodies) {
+            // Bodies of one species share the type and the color
+            for (species_i, species) in
+                user_constants.species.iter().enumerate()
+            {
+                let color = match species.color {
+                    Some([r, g, b]) => Color::from_rgba(r, g, b, 255),
+                    None => palette.next_color(),
+                };
+
+                for _ in 0..species.n {
+                    Body::randomly_spawn_body(
+                        &mut world.bodies,
+                        species,
+                        species_i as u32 + 1,
+                        color,
+                        &mut world.ids,
+                        user_constants,
+                        &mut world.rng,
+                    );
+                }
+            }
+        }
+
+        // Spawn the plants
+        if scenario.is_none_or(|scenario| scenario.config_plants) {
+            for _ in 0..*PLANTS_N.read().unwrap() {
+                Plant::randomly_spawn_plant(
+                    &world.bodies,
+                    &mut world.plants,
+                    &mut world.ids,
+                    &mut world.rng,
+                );
+            }
+        }
+
+        world
+    }
+
+    /// Apply the interventions of the tick. They don't take anything
+    /// from the random numbers of the evolution, so the run goes the
+    /// same way when they're replayed.
+    pub fn apply_interventions(
+        &mut self,
+        interventions: &[Intervention],
+        user_constants: &UserConstants,
+    ) {
+        let mut rng = get_object_rng(self.seed, &self.tick);
+
+        for intervention in interventions {
+            intervention.apply(
+                &mut self.bodies,
+                &mut self.plants,
+                &mut self.crosses,
+                &mut self.ids,
+                user_constants,
+                &mut rng,
+            );
+        }
+    }
+
+    /// Go one tick further.
+    pub fn update(
+        &mut self,
+        user_constants: &UserConstants,
+        thread_pool: &ThreadPool,
+    ) -> TickEvents {
+        let mut events = TickEvents::default();
+
+        Condition::update_conditions(
+            &mut self.conditions,
+            user_constants,
+            &mut self.rng,
+        );
+
+        // Remove plants
+        let n_to_remove = (self.plants.len() as f32
+            * user_constants.plant_die_chance)
+            as usize;
+
+        for (random_plant_id, random_plant) in
+            self.plants.choose_multiple(&mut self.rng, n_to_remove)
+        {
+            self.removed_plants
+                .insert(*random_plant_id, random_plant.pos);
+        }
+
+        // Spawn a plant in a random place with a specific chance
+        for _ in 0..*PLANTS_N_FOR_ONE_STEP.read().unwrap() {
+            Plant::randomly_spawn_plant(
+                &self.bodies,
+                &mut self.plants,
+                &mut self.ids,
+                &mut self.rng,
+            );
+        }
+
+        // Apply the conditions to the plants within their regions
+        for region in &self.conditions {
+            match region.condition {
+                Condition::Drought => {
+                    Plant::kill_plants_in_region(
+                        region,
+                        &self.plants,
+                        &mut self.removed_plants,
+                        user_constants,
+                        &mut self.rng,
+                    );
+                }
+                Condition::Rain => {
+                    Plant::spawn_plants_in_region(
+                        region,
+                        &self.bodies,
+                        &mut self.plants,
+                        &mut self.ids,
+                        user_constants,
+                        &mut self.rng,
+                    );
+                }
+            }
+        }
+
+        // Every body decides what to do from the state at the start of
+        // the tick, so the bodies can decide on multiple threads
+        let tick_seed = self.rng.gen::<u64>();
+
+        thread_pool.install(|| {
+            self.decisions.par_extend(
+                self.bodies.par_iter().map_init(
+                    Vec::new,
+                    |visible_bodies, (body_id, body)| {
+                        let (next_body, action) = body.decide(
+                            body_id,
+                            &self.bodies,
+                            &self.plants,
+                            &self.crosses,
+                            &self.removed_plants,
+                            &self.conditions,
+                            user_constants,
+                            visible_bodies,
+                            &mut get_object_rng(tick_seed, body_id),
+                        );
+
+                        (*body_id, next_body, action)
+                    },
+                ),
+            )
+        });
+
+        // The older bodies go first, so they win when several bodies have
+        // reached the same food
+        self.decisions
+            .sort_unstable_by_key(|(body_id, _, _)| *body_id);
+
+        for (body_id, mut body, action) in self.decisions.drain(..) {
+            // The body has been eaten by an older one
+            if self.removed_bodies.contains_key(&body_id) {
+                continue;
+            }
+
+            match action {
+                Action::None => (),
+                Action::Die => {
+                    self.removed_bodies.insert(body_id, body.pos);
+                    events.deaths.push(body.pos);
+                }
+                Action::Eat(food) => {
+                    let is_food_left = match food.food_type {
+                        ObjectType::Body => !self
+                            .removed_bodies
+                            .contains_key(&food.id),
+                        ObjectType::Cross => self
+                            .crosses
+                            .remove(&food.id, food.pos)
+                            .is_some(),
+                        ObjectType::Plant => !self
+                            .removed_plants
+                            .contains_key(&food.id),
+                    };
+
+                    if is_food_left {
+                        body.energy += match body.eating_strategy {
+                            EatingStrategy::Omnivorous => {
+                                food.energy
+                                    * user_constants
+                                        .omnivorous_food_part
+                            }
+                            EatingStrategy::Herbivorous
+                            | EatingStrategy::Carnivorous => {
+                                food.energy
+                            }
+                        };
+
+                        if let Some(viruses) = &food.viruses {
+                            body.get_viruses(viruses, user_constants);
+                        }
+
+                        match food.food_type {
+                            ObjectType::Body => {
+                                self.removed_bodies
+                                    .insert(food.id, food.pos);
+                                events.deaths.push(food.pos);
+                            }
+                            ObjectType::Plant => {
+                                self.removed_plants
+                                    .insert(food.id, food.pos);
+                            }
+                            ObjectType::Cross => (),
+                        }
+                    }
+                }
+                Action::Procreate(children) => {
+                    let children_ids = children.map(|child| {
+                        let child_id = self.ids.next_id();
+                        self.new_bodies.push((child_id, child));
+
+                        child_id
+                    });
+
+                    events.divisions.push((body_id, children_ids));
+
+                    self.removed_bodies.insert(body_id, body.pos);
+                }
+            }
+
+            // Where the body is drawn from until the next tick
+            body.prev_pos = body.pos;
+
+            // Only the bodies that have left their cells have to be
+            // moved to other cells
+            if CELLS.get_cell_by_pos(body.last_pos)
+                == CELLS.get_cell_by_pos(body.pos)
+            {
+                body.pos = body.last_pos;
+            } else {
+                self.migrating_bodies.push((body_id, body.pos));
+            }
+
+            // The position is still within the cell
+            self.bodies.insert(body_id, body);
+        }
+
+        self.crosses.retain(|_, cross| {
+            cross.age += 1;
+
+            cross.age
+                <= user_constants.cross_lifespan * SECOND_TICKS_N
+        });
+
+        for (body_id, body_pos) in self.removed_bodies.drain() {
+            let body =
+                self.bodies.remove(&body_id, body_pos).unwrap();
+
+            if let Status::Cross = body.status {
+                self.crosses.insert(body_id, Cross::new(&body));
+            }
+        }
+
+        for (body_id, body_pos) in self.migrating_bodies.drain(..) {
+            self.bodies.relocate(&body_id, body_pos, |body| {
+                body.pos = body.last_pos
+            });
+        }
+
+        for (new_body_id, new_body) in self.new_bodies.drain(..) {
+            self.bodies.insert(new_body_id, new_body);
+        }
+
+        for (plant_id, plant_pos) in self.removed_plants.drain() {
+            self.plants.remove(&plant_id, plant_pos);
+        }
+
+        self.tick += 1;
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_config;
+    use rayon::ThreadPoolBuilder;
+    use std::fs::read_to_string;
+
+    const SEED: u64 = 42;
+    const TICKS_N: usize = 300;
+
+    /// The bodies and the plants sorted by their IDs.
+    type State = (Vec<(BodyId, Body)>, Vec<(PlantId, Plant)>);
+
+    /// The state after `TICKS_N` ticks on `threads` threads.
+    fn run(threads: usize) -> State {
+        let mut user_constants =
+            parse_config(&read_to_string(CONFIG_FILE_NAME).unwrap())
+                .unwrap();
+
+        // Fewer bodies keep the test fast
+        for species in &mut user_constants.species {
+            species.n = 20;
+        }
+
+        let thread_pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        let mut world = World::new(
+            SEED,
+            None,
+            &mut Palette::new(SEED),
+            &user_constants,
+        );
+
+        for _ in 0..TICKS_N {
+            world.update(&user_constants, &thread_pool);
+        }
+
+        let mut bodies = world
+            .bodies
+            .iter()
+            .map(|(id, body)| (*id, *body))
+            .collect::<Vec<_>>();
+        bodies.sort_unstable_by_key(|(id, _)| *id);
+
+        let mut plants = world
+            .plants
+            .iter()
+            .map(|(id, plant)| (*id, plant.clone()))
+            .collect::<Vec<_>>();
+        plants.sort_unstable_by_key(|(id, _)| *id);
+
+        (bodies, plants)
+    }
+
+    #[test]
+    fn same_for_any_number_of_threads() {
+        let (bodies, plants) = run(1);
+
+        assert!(!bodies.is_empty());
+        assert!((bodies, plants) == run(4));
+    }
+}
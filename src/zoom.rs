@@ -1,53 +1,180 @@
-use crate::{
-    adjusted_pos, constants::*, screen_height, screen_width,
-    AREA_SIZE,
-};
+use crate::{constants::*, UserConstants, AREA_SIZE};
 use macroquad::{
     camera::{set_camera, Camera2D},
+    input::{
+        is_key_down, is_key_pressed, is_mouse_button_down,
+        is_mouse_button_pressed, is_mouse_button_released,
+        mouse_position, mouse_wheel, KeyCode, MouseButton,
+    },
     math::{vec2, Rect, Vec2},
 };
 
 pub struct Zoom {
     /// Whether we're zoomed in.
-    pub zoomed:         bool,
-    /// The width of the part of the area size the zoom shows.
-    pub scaling_width:  f32,
-    /// The height of the part of the area size the zoom shows.
-    pub scaling_height: f32,
+    pub zoomed:        bool,
+    /// How many times the area is magnified.
+    pub scale:         f32,
     /// The position of the center of the zoom rectangle.
-    pub center_pos:     Option<Vec2>,
-    pub mouse_pos:      Option<Vec2>,
+    pub center_pos:    Option<Vec2>,
+    /// Where the cursor was during the last frame.
+    pub mouse_pos:     Option<Vec2>,
+    /// Whether the camera stays where it is instead of following the
+    /// cursor.
+    pub locked:        bool,
+    /// How far the cursor has moved since the left mouse button was
+    /// pressed, `None` if it isn't held.
+    pub drag_distance: Option<f32>,
     /// The rectangle on the same place as the camera.
-    pub rect:           Option<Rect>,
+    pub rect:          Option<Rect>,
     /// Normal rect size + OBJECT_RADIUS * 2.0.
-    pub extended_rect:  Option<Rect>,
+    pub extended_rect: Option<Rect>,
 }
 
 #[inline(always)]
-/// Set the camera zoom to where the mouse cursor is.
-pub fn get_zoom_target(camera: &mut Camera2D, zoom: &mut Zoom) {
-    let size = vec2(
-        screen_width() / MAX_ZOOM * OBJECT_RADIUS,
-        screen_height() / MAX_ZOOM * OBJECT_RADIUS,
-    );
+/// The position of the area that's under the screen position when
+/// unzoomed.
+fn get_area_pos(screen_pos: Vec2) -> Vec2 {
+    // OBJECT_RADIUS is equal to one pixel when unzoomed
+    screen_pos * OBJECT_RADIUS
+}
+
+/// Zoom with the mouse wheel, pan by dragging with the left mouse button
+/// or with the arrow keys, and lock the view. Get where the left mouse
+/// button has been clicked without dragging.
+pub fn handle_camera_input(
+    camera: &Camera2D,
+    zoom: &mut Zoom,
+    are_keys_free: bool,
+    user_constants: &UserConstants,
+) -> Option<Vec2> {
+    let current_mouse_pos = Vec2::from(mouse_position());
+    let mouse_shift = current_mouse_pos
+        - zoom.mouse_pos.unwrap_or(current_mouse_pos);
+    zoom.mouse_pos = Some(current_mouse_pos);
+
+    if are_keys_free && is_key_pressed(KeyCode::L) {
+        zoom.locked = !zoom.locked;
+    }
+
+    let (_, wheel) = mouse_wheel();
+
+    if wheel != 0.0 {
+        let scale = (zoom.scale * ZOOM_STEP.powf(wheel.signum()))
+            .clamp(MIN_ZOOM, user_constants.max_zoom);
+
+        // The position under the cursor stays where it is
+        let pos = camera.screen_to_world(current_mouse_pos);
+        let screen_part =
+            get_area_pos(current_mouse_pos) / *AREA_SIZE;
+
+        zoom.center_pos =
+            Some(pos - (screen_part - 0.5) * *AREA_SIZE / scale);
+        zoom.scale = scale;
+    }
+
+    let mut pan = Vec2::ZERO;
+
+    for (key, direction) in [
+        (KeyCode::Left, vec2(-1.0, 0.0)),
+        (KeyCode::Right, vec2(1.0, 0.0)),
+        (KeyCode::Up, vec2(0.0, -1.0)),
+        (KeyCode::Down, vec2(0.0, 1.0)),
+    ] {
+        if is_key_down(key) {
+            pan += direction * PAN_SPEED;
+        }
+    }
+
+    let mut click = None;
+
+    if is_mouse_button_pressed(MouseButton::Left) {
+        zoom.drag_distance = Some(0.0);
+    } else if let Some(drag_distance) = &mut zoom.drag_distance {
+        *drag_distance += mouse_shift.length();
+
+        if *drag_distance > DRAG_THRESHOLD {
+            pan -= mouse_shift;
+        }
+
+        if is_mouse_button_released(MouseButton::Left)
+            || !is_mouse_button_down(MouseButton::Left)
+        {
+            if *drag_distance <= DRAG_THRESHOLD {
+                click = Some(current_mouse_pos);
+            }
 
-    zoom.center_pos = Some(adjusted_pos(zoom.mouse_pos.unwrap()));
+            zoom.drag_distance = None;
+        }
+    }
+
+    if zoom.zoomed && pan != Vec2::ZERO {
+        // Panning means the camera has been moved on purpose
+        zoom.locked = true;
+        zoom.center_pos = Some(
+            zoom.center_pos.unwrap() + get_area_pos(pan) / zoom.scale,
+        );
+    } else if !zoom.locked {
+        zoom.center_pos = Some(get_area_pos(current_mouse_pos));
+    }
+
+    click
+}
+
+/// Zoom in to `max_zoom` where `screen_pos` is or zoom out completely.
+pub fn toggle_zoom(
+    zoom: &mut Zoom,
+    screen_pos: Vec2,
+    user_constants: &UserConstants,
+) {
+    if zoom.zoomed {
+        zoom.scale = MIN_ZOOM;
+    } else {
+        zoom.scale = user_constants.max_zoom;
+        zoom.center_pos = Some(get_area_pos(screen_pos));
+    }
+}
+
+#[inline(always)]
+/// Point the camera at the center of the zoom, keeping what it shows
+/// within the area.
+pub fn update_camera(camera: &mut Camera2D, zoom: &mut Zoom) {
+    zoom.zoomed = zoom.scale > MIN_ZOOM;
+
+    if !zoom.zoomed {
+        zoom.rect = None;
+        zoom.extended_rect = None;
+
+        default_camera(camera);
+
+        return;
+    }
+
+    let size = *AREA_SIZE / zoom.scale;
+    let center_pos = zoom
+        .center_pos
+        .unwrap_or(*AREA_SIZE / 2.0)
+        .clamp(size / 2.0, *AREA_SIZE - size / 2.0);
+
+    zoom.center_pos = Some(center_pos);
     zoom.rect = Some(Rect::new(
-        zoom.center_pos.unwrap().x - size.x / 2.0,
-        zoom.center_pos.unwrap().y - size.y / 2.0,
+        center_pos.x - size.x / 2.0,
+        center_pos.y - size.y / 2.0,
         size.x,
         size.y,
     ));
 
     zoom.extended_rect = Some(Rect::new(
-        zoom.center_pos.unwrap().x - size.x / 2.0 - OBJECT_RADIUS,
-        zoom.center_pos.unwrap().y - size.y / 2.0 - OBJECT_RADIUS,
+        center_pos.x - size.x / 2.0 - OBJECT_RADIUS,
+        center_pos.y - size.y / 2.0 - OBJECT_RADIUS,
         size.x + OBJECT_RADIUS * 2.0,
         size.y + OBJECT_RADIUS * 2.0,
     ));
 
-    camera.target = zoom.center_pos.unwrap();
-    camera.zoom = vec2(zoom.scaling_width, zoom.scaling_height);
+    camera.target = center_pos;
+    camera.zoom = vec2(
+        zoom.scale / AREA_SIZE.x * 2.0,
+        zoom.scale / AREA_SIZE.y * 2.0,
+    );
 
     set_camera(camera);
 }